@@ -0,0 +1,391 @@
+use wasm_bindgen::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::blue_noise::{blue_noise_mask, mask_threshold, DEFAULT_MASK_SIZE};
+use crate::error_diffusion::DiffusionKernel;
+use crate::selective::{dither_plane, AlgorithmParams, DitheringAlgorithmType};
+use crate::void_and_cluster::{void_and_cluster_mask, DEFAULT_MATRIX_SIZE};
+
+// Temporally coherent dithering for animations
+//
+// Dithering every frame on its own makes static areas shimmer: error diffusion
+// reroutes its error as soon as anything upstream changes, and the random and
+// blue-noise algorithms draw a new pattern per call. An AnimationDitherer keeps
+// state between frames instead:
+//
+// - Noise and mask algorithms get one threshold field per animation (seeded
+//   noise, or a seeded mask generated once) so the pattern never moves.
+// - Pixels whose source is unchanged (within a tolerance) keep their previous
+//   decision; error diffusion still diffuses their error so tone is preserved.
+//   "Unchanged" is measured against the source value the pixel was last
+//   decided at, not the previous frame, so a slow fade that moves less than
+//   the tolerance per frame still re-dithers once it has drifted that far.
+// - Hysteresis moves the threshold away from the previous decision, so small
+//   motion and noise do not flip a pixel back and forth.
+//
+// Output is one byte per pixel (0 or 255), like selective::dither_plane.
+
+// How frames are stabilized for the chosen algorithm
+enum Stabilizer {
+    // White where value >= field[i]; built once per animation
+    ThresholdField(Vec<f32>),
+    // Error diffusion with the algorithm's kernel
+    Diffusion(DiffusionKernel),
+    // Deterministic algorithms: dither the frame, then keep unchanged pixels
+    Frame,
+}
+
+// Settings that control how strongly frames are tied together
+#[derive(Clone, Copy, Debug)]
+pub struct TemporalOptions {
+    pub seed: u64,             // Seed for the random noise field and the blue-noise and void-and-cluster masks
+    pub reuse_unchanged: bool, // Keep the previous decision where the source is unchanged
+    pub change_tolerance: u8,  // Largest source difference still counted as unchanged
+    pub hysteresis: f32,       // Threshold shift (0 - 255) towards the previous decision
+}
+
+impl Default for TemporalOptions {
+    fn default() -> Self {
+        TemporalOptions { seed: 0, reuse_unchanged: true, change_tolerance: 4, hysteresis: 24.0 }
+    }
+}
+
+// How much a frame's output changed from the previous one
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Flicker {
+    pub changed: f32,        // Fraction of all pixels that flipped
+    pub changed_static: f32, // Fraction of unchanged source pixels that flipped anyway
+}
+
+// Compare two consecutive dithered frames (one byte per pixel); a pixel counts as
+// static when its source is within the tolerance of the reference it was last decided at
+pub fn flicker_metric(
+    previous_output: &[u8],
+    output: &[u8],
+    reference_source: &[u8],
+    source: &[u8],
+    change_tolerance: u8,
+) -> Flicker {
+    let mut flipped = 0usize;
+    let mut static_pixels = 0usize;
+    let mut static_flipped = 0usize;
+    for i in 0..output.len() {
+        let flip = (previous_output[i] > 127) != (output[i] > 127);
+        let unchanged = reference_source[i].abs_diff(source[i]) <= change_tolerance;
+        flipped += flip as usize;
+        static_pixels += unchanged as usize;
+        static_flipped += (flip && unchanged) as usize;
+    }
+    Flicker {
+        changed: flipped as f32 / output.len().max(1) as f32,
+        changed_static: static_flipped as f32 / static_pixels.max(1) as f32,
+    }
+}
+
+// Values of a mask_size x mask_size mask tiled over `size` pixels of rows `width` wide
+fn tiled(mask: &[f32], mask_size: usize, width: usize, size: usize) -> impl Iterator<Item = f32> + '_ {
+    (0..size).map(move |i| mask[(i / width % mask_size) * mask_size + i % width % mask_size])
+}
+
+// Dithers a sequence of same-sized frames, carrying state from one frame to the next
+#[wasm_bindgen]
+pub struct AnimationDitherer {
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: AlgorithmParams,
+    options: TemporalOptions,
+    stabilizer: Stabilizer,
+    reference_source: Option<Vec<u8>>, // Source value each pixel was last decided at
+    previous_output: Option<Vec<u8>>,
+    flicker: Flicker,
+}
+
+impl AnimationDitherer {
+    pub fn with_options(
+        width: usize,
+        height: usize,
+        algo_type: DitheringAlgorithmType,
+        params: AlgorithmParams,
+        options: TemporalOptions,
+    ) -> Self {
+        let size = width * height;
+        let threshold = params.threshold as f32;
+        let stabilizer = match algo_type {
+            DitheringAlgorithmType::FloydSteinberg => Stabilizer::Diffusion(DiffusionKernel::FloydSteinberg),
+            DitheringAlgorithmType::Atkinson => Stabilizer::Diffusion(DiffusionKernel::Atkinson),
+            DitheringAlgorithmType::SierraLite => Stabilizer::Diffusion(DiffusionKernel::SierraLite),
+            DitheringAlgorithmType::Burkes => Stabilizer::Diffusion(DiffusionKernel::Burkes),
+            DitheringAlgorithmType::Stucki => Stabilizer::Diffusion(DiffusionKernel::Stucki),
+            DitheringAlgorithmType::JarvisJudiceNinke => Stabilizer::Diffusion(DiffusionKernel::JarvisJudiceNinke),
+            DitheringAlgorithmType::Random => {
+                // Same noise as random_dither, drawn once from the seed
+                let mut rng = StdRng::seed_from_u64(options.seed);
+                let field = (0..size)
+                    .map(|_| threshold - (rng.gen::<f32>() - 0.5) * params.noise_amount)
+                    .collect();
+                Stabilizer::ThresholdField(field)
+            }
+            DitheringAlgorithmType::BlueNoise => {
+                // Same threshold as blue_noise_dither on a mask from the seed
+                let mask = blue_noise_mask(DEFAULT_MASK_SIZE, options.seed);
+                let field = tiled(&mask, DEFAULT_MASK_SIZE, width, size)
+                    .map(|n| mask_threshold(n, params.threshold))
+                    .collect();
+                Stabilizer::ThresholdField(field)
+            }
+            DitheringAlgorithmType::VoidAndCluster => {
+                // Same mask size as the still-image dither, generated from the seed
+                let mask = void_and_cluster_mask(DEFAULT_MATRIX_SIZE, options.seed);
+                let field = tiled(&mask, DEFAULT_MATRIX_SIZE, width, size).map(|n| n * 255.0).collect();
+                Stabilizer::ThresholdField(field)
+            }
+            _ => Stabilizer::Frame,
+        };
+
+        AnimationDitherer {
+            width,
+            height,
+            algo_type,
+            params,
+            options,
+            stabilizer,
+            reference_source: None,
+            previous_output: None,
+            flicker: Flicker::default(),
+        }
+    }
+
+    // Threshold shifted away from the previous decision at pixel i
+    fn hysteresis_offset(&self, i: usize) -> f32 {
+        match &self.previous_output {
+            Some(previous) if previous[i] > 127 => -self.options.hysteresis,
+            Some(_) => self.options.hysteresis,
+            None => 0.0,
+        }
+    }
+
+    // Previous decision for pixel i if its source is still within the tolerance
+    // of the value it was decided at and reuse is on
+    fn reused_decision(&self, source: &[u8], i: usize) -> Option<u8> {
+        if !self.options.reuse_unchanged { return None; }
+        let reference_source = self.reference_source.as_ref()?;
+        let previous_output = self.previous_output.as_ref()?;
+        if reference_source[i].abs_diff(source[i]) <= self.options.change_tolerance {
+            Some(previous_output[i])
+        } else {
+            None
+        }
+    }
+
+    // Dither one grayscale frame and return one byte per pixel
+    pub fn dither(&mut self, source: &[u8]) -> Vec<u8> {
+        let (width, height) = (self.width, self.height);
+        let size = width * height;
+        let mut output = vec![0u8; size];
+
+        match &self.stabilizer {
+            Stabilizer::ThresholdField(field) => {
+                for i in 0..size {
+                    output[i] = match self.reused_decision(source, i) {
+                        Some(previous) => previous,
+                        None => {
+                            let threshold = field[i] + self.hysteresis_offset(i);
+                            if (source[i] as f32) < threshold { 0 } else { 255 }
+                        }
+                    };
+                }
+            }
+            Stabilizer::Diffusion(kernel) => {
+                let (kernel, divisor) = kernel.weights();
+                let mut buffer: Vec<f32> = source.iter().map(|&v| v as f32).collect();
+                let threshold = self.params.threshold as f32;
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        let value = buffer[i];
+                        let decision = match self.reused_decision(source, i) {
+                            Some(previous) => previous,
+                            None => {
+                                if value < threshold + self.hysteresis_offset(i) { 0 } else { 255 }
+                            }
+                        };
+                        output[i] = decision;
+
+                        // Forced decisions still pass on their error so tone stays right
+                        let error = value - decision as f32;
+                        for &(dx, dy, weight) in kernel.iter() {
+                            let nx = x as isize + dx;
+                            let ny = y + dy;
+                            if nx < 0 || nx >= width as isize || ny >= height { continue; }
+                            buffer[ny * width + nx as usize] += error * weight / divisor;
+                        }
+                    }
+                }
+            }
+            Stabilizer::Frame => {
+                output = dither_plane(source, width, height, self.algo_type, &self.params);
+                for (i, pixel) in output.iter_mut().enumerate() {
+                    if let Some(previous) = self.reused_decision(source, i) {
+                        *pixel = previous;
+                    }
+                }
+            }
+        }
+
+        self.flicker = match (&self.previous_output, &self.reference_source) {
+            (Some(previous_output), Some(reference_source)) => {
+                flicker_metric(previous_output, &output, reference_source, source, self.options.change_tolerance)
+            }
+            _ => Flicker::default(),
+        };
+        // Only recomputed pixels move their reference
+        let mut reference = match self.reference_source.take() {
+            Some(reference) if self.options.reuse_unchanged => reference,
+            _ => source.to_vec(),
+        };
+        for (r, &s) in reference.iter_mut().zip(source) {
+            if r.abs_diff(s) > self.options.change_tolerance {
+                *r = s;
+            }
+        }
+        self.reference_source = Some(reference);
+        self.previous_output = Some(output.clone());
+        output
+    }
+
+    pub fn last_flicker(&self) -> Flicker {
+        self.flicker
+    }
+}
+
+#[wasm_bindgen]
+impl AnimationDitherer {
+    // New ditherer for width x height frames; unlisted parameters use the editor defaults
+    #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: usize,
+        height: usize,
+        algorithm: DitheringAlgorithmType,
+        threshold: u8,
+        dot_size: usize,
+        noise_amount: f32,
+        seed: u32,
+        reuse_unchanged: bool,
+        change_tolerance: u8,
+        hysteresis: f32,
+    ) -> AnimationDitherer {
+        let params = AlgorithmParams { threshold, dot_size, noise_amount, ..AlgorithmParams::default() };
+        let options = TemporalOptions { seed: seed as u64, reuse_unchanged, change_tolerance, hysteresis };
+        AnimationDitherer::with_options(width, height, algorithm, params, options)
+    }
+
+    // Dither the next frame (one byte per pixel in and out)
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn dither_frame(&mut self, input_ptr: *const u8, output_ptr: *mut u8) {
+        let size = self.width * self.height;
+        let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+        output.copy_from_slice(&self.dither(input));
+    }
+
+    // Fraction of pixels that flipped in the last frame
+    pub fn flicker(&self) -> f32 {
+        self.flicker.changed
+    }
+
+    // Fraction of unchanged source pixels that flipped in the last frame
+    pub fn static_flicker(&self) -> f32 {
+        self.flicker.changed_static
+    }
+
+    // Forget the previous frame, e.g. at a scene cut
+    pub fn reset(&mut self) {
+        self.reference_source = None;
+        self.previous_output = None;
+        self.flicker = Flicker::default();
+    }
+}
+
+// Dither frame_count consecutive frames and return the mean flicker across frame pairs
+#[allow(clippy::too_many_arguments, clippy::not_unsafe_ptr_arg_deref)]
+pub fn dither_animation(
+    frames_ptr: *const u8,
+    frame_count: usize,
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: AlgorithmParams,
+    options: TemporalOptions,
+    output_ptr: *mut u8,
+) -> f32 {
+    let size = width * height;
+    if size == 0 || frame_count == 0 { return 0.0; }
+    let frames = unsafe { std::slice::from_raw_parts(frames_ptr, size * frame_count) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * frame_count) };
+
+    let mut ditherer = AnimationDitherer::with_options(width, height, algo_type, params, options);
+    let mut total_flicker = 0.0;
+    for (frame, out) in frames.chunks_exact(size).zip(output.chunks_exact_mut(size)) {
+        out.copy_from_slice(&ditherer.dither(frame));
+        total_flicker += ditherer.last_flicker().changed;
+    }
+    if frame_count > 1 { total_flicker / (frame_count - 1) as f32 } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fraction of white pixels in a dithered frame
+    fn coverage(frame: &[u8]) -> f32 {
+        frame.iter().filter(|&&p| p > 127).count() as f32 / frame.len() as f32
+    }
+
+    #[test]
+    fn slow_fade_tracks_the_source() {
+        // 40 to 217 over 60 frames: about 3 levels per frame, under the default tolerance of 4
+        let (width, height, frames) = (64, 64, 60);
+        let options = TemporalOptions::default();
+        // Hysteresis may hold the tone back by up to its shift
+        let allowed = options.hysteresis / 255.0 + 0.03;
+        for algo_type in [DitheringAlgorithmType::FloydSteinberg, DitheringAlgorithmType::BlueNoise, DitheringAlgorithmType::Ordered] {
+            let params = AlgorithmParams::default();
+            let mut ditherer = AnimationDitherer::with_options(width, height, algo_type, params, options);
+            for f in 0..frames {
+                let frame = vec![40 + (177 * f / (frames - 1)) as u8; width * height];
+                let expected = coverage(&dither_plane(&frame, width, height, algo_type, &params));
+                let actual = coverage(&ditherer.dither(&frame));
+                assert!((actual - expected).abs() < allowed, "{:?} frame {}: coverage {}, still image {}", algo_type, f, actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_fields_follow_the_seed() {
+        let (width, height) = (80, 70);
+        let field = |algo_type, seed| {
+            let options = TemporalOptions { seed, ..TemporalOptions::default() };
+            match AnimationDitherer::with_options(width, height, algo_type, AlgorithmParams::default(), options).stabilizer {
+                Stabilizer::ThresholdField(field) => field,
+                _ => panic!("{:?} has no threshold field", algo_type),
+            }
+        };
+        for algo_type in [DitheringAlgorithmType::BlueNoise, DitheringAlgorithmType::VoidAndCluster] {
+            let first = field(algo_type, 1);
+            assert_eq!(first.len(), width * height);
+            assert_eq!(first, field(algo_type, 1), "{:?} is not stable for a seed", algo_type);
+            assert_ne!(first, field(algo_type, 2), "{:?} ignores the seed", algo_type);
+        }
+    }
+
+    #[test]
+    fn empty_frames_do_not_panic() {
+        let mut output = [0u8; 0];
+        let flicker = dither_animation([0u8; 0].as_ptr(), 3, 0, 4, DitheringAlgorithmType::FloydSteinberg,
+            AlgorithmParams::default(), TemporalOptions::default(), output.as_mut_ptr());
+        assert_eq!(flicker, 0.0);
+    }
+}
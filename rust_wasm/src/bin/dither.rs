@@ -0,0 +1,1091 @@
+use clap::{Parser, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use dithering_wasm::decode::{load_frames, load_image, Image};
+use dithering_wasm::*;
+
+// Command-line dithering
+//
+// Runs the same exported functions the web app calls, on image files instead
+// of wasm memory. Every algorithm parameter is a flag that defaults to the
+// editor's value; algorithms ignore the flags they do not use. Inputs may be
+// glob patterns, and batches are written next to each other in an output
+// directory using the input file names. GIF output is the exception: every
+// frame of every input, in order, becomes one animation dithered with
+// temporal coherence.
+
+#[derive(Parser, Debug)]
+#[command(name = "dither", version, about = "Dither images with the same algorithms as the web editor")]
+struct Cli {
+    /// Input files or glob patterns (quote globs to keep the shell from expanding them)
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// Output file for a single input, or output directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format (default: from the output extension, else png)
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Dithering algorithm
+    #[arg(short, long, value_enum, default_value_t = Algorithm::Halftone)]
+    algorithm: Algorithm,
+
+    /// Threshold for error diffusion and threshold-based algorithms
+    #[arg(long, default_value_t = 128)]
+    threshold: u8,
+
+    /// Dot size (ordered matrix scale, halftone dot, FM dot, multi-tone scale)
+    #[arg(long, default_value_t = 3)]
+    dot_size: usize,
+
+    /// Halftone spacing between dots
+    #[arg(long, default_value_t = 5)]
+    spacing: usize,
+
+    /// Screen angle in degrees
+    #[arg(long, default_value_t = 45.0)]
+    angle: f32,
+
+    /// Random dithering noise amount
+    #[arg(long, default_value_t = 50.0)]
+    noise: f32,
+
+    /// Pattern dithering pattern
+    #[arg(long, value_enum, default_value_t = PatternArg::Dots)]
+    pattern: PatternArg,
+
+    /// Pattern cell size
+    #[arg(long, default_value_t = 4)]
+    pattern_size: usize,
+
+    /// Tone levels (multi-tone) or levels per channel (rgb)
+    #[arg(long, default_value_t = 4)]
+    levels: usize,
+
+    /// Algorithm used by multi-tone
+    #[arg(long, value_enum, default_value_t = ToneAlgorithmArg::Ordered)]
+    tone_algorithm: ToneAlgorithmArg,
+
+    /// Screen ruling in lines per inch (halftone-screen, clustered-dot, cmyk)
+    #[arg(long, default_value_t = 60.0)]
+    lpi: f32,
+
+    /// Device resolution in dots per inch (screens, SVG physical size, DBS eye model)
+    #[arg(long, default_value_t = 300.0)]
+    dpi: f32,
+
+    /// Spot function for clustered-dot and cmyk screens
+    #[arg(long, value_enum, default_value_t = ShapeArg::Round)]
+    shape: ShapeArg,
+
+    /// Rank mask for FM screening
+    #[arg(long, value_enum, default_value_t = FmMaskArg::VoidAndCluster)]
+    fm_mask: FmMaskArg,
+
+    /// CMYK black separation
+    #[arg(long, value_enum, default_value_t = SeparationArg::Gcr)]
+    separation: SeparationArg,
+
+    /// CMYK black generation strength (GCR, 0 - 1)
+    #[arg(long, default_value_t = 1.0)]
+    black_generation: f32,
+
+    /// Gray component where black starts (GCR, 0 - 1)
+    #[arg(long, default_value_t = 0.0)]
+    black_start: f32,
+
+    /// Under color removal strength (UCR, 0 - 1)
+    #[arg(long, default_value_t = 1.0)]
+    under_color_removal: f32,
+
+    /// Total ink limit (0 - 4)
+    #[arg(long, default_value_t = 3.0)]
+    ink_limit: f32,
+
+    /// Write the four CMYK plates as separate files instead of a preview
+    #[arg(long)]
+    plates: bool,
+
+    /// Algorithm applied to each channel in rgb mode
+    #[arg(long, value_enum, default_value_t = ChannelAlgorithmArg::FloydSteinberg)]
+    channel_algorithm: ChannelAlgorithmArg,
+
+    /// Offset and rotate mask-based algorithms between RGB channels
+    #[arg(long)]
+    decorrelate: bool,
+
+    /// SVG element grouping for traced output
+    #[arg(long, value_enum, default_value_t = SvgModeArg::Rects)]
+    svg_mode: SvgModeArg,
+
+    /// Drop traced regions smaller than this many pixels (0 = exact)
+    #[arg(long, default_value_t = 0)]
+    despeckle: usize,
+
+    /// Physical unit for SVG width and height
+    #[arg(long, value_enum, default_value_t = SvgUnitArg::Px)]
+    svg_unit: SvgUnitArg,
+
+    /// Edge detector for the edges, line-art and edge-regions segments algorithms (gradient operator for edge-aware-diffusion)
+    #[arg(long, value_enum, default_value_t = EdgeDetectorArg::Canny)]
+    edge_detector: EdgeDetectorArg,
+
+    /// Edge map to write: binary edges, gradient magnitude or gradient orientation
+    #[arg(long, value_enum, default_value_t = EdgeOutputArg::Binary)]
+    edge_output: EdgeOutputArg,
+
+    /// Gaussian smoothing (pixels) before edge detection, line-art and edge-aware-diffusion; 0 for none
+    #[arg(long, default_value_t = 1.4)]
+    sigma: f32,
+
+    /// Canny low threshold (edges: --threshold is the high one; line-art: --edge-threshold)
+    #[arg(long, default_value_t = 48.0)]
+    low_threshold: f32,
+
+    /// Segmentation method for the segments algorithm
+    #[arg(long, value_enum, default_value_t = SegmentationArg::Slic)]
+    segmentation: SegmentationArg,
+
+    /// SLIC superpixel count or k-means cluster count
+    #[arg(long, default_value_t = 64)]
+    regions: usize,
+
+    /// SLIC compactness: higher keeps superpixels closer to squares
+    #[arg(long, default_value_t = 10.0)]
+    compactness: f32,
+
+    /// Smallest region in pixels; smaller pieces merge into a neighbour
+    #[arg(long, default_value_t = 64)]
+    min_region_size: usize,
+
+    /// Seed for k-means initial centers and the blue-noise mask
+    #[arg(long, default_value_t = 0)]
+    seed: u32,
+
+    /// Algorithm for the fill under line-art strokes and outside every selective mask
+    #[arg(long, value_enum, default_value_t = ChannelAlgorithmArg::BlueNoise)]
+    fill: ChannelAlgorithmArg,
+
+    /// Region mask for selective (repeat for several; nonzero = inside, the first matching mask wins)
+    #[arg(long = "mask", value_name = "FILE")]
+    masks: Vec<PathBuf>,
+
+    /// Algorithm for each --mask, in order; the last one repeats for further masks
+    #[arg(long = "mask-algorithm", value_enum, value_name = "ALGORITHM", default_value = "floyd-steinberg")]
+    mask_algorithms: Vec<ChannelAlgorithmArg>,
+
+    /// Blue-noise mask size in pixels (tiled over the image)
+    #[arg(long, default_value_t = 64)]
+    mask_size: usize,
+
+    /// Line-art stroke width in pixels
+    #[arg(long, default_value_t = 1.0)]
+    stroke_width: f32,
+
+    /// Edge threshold for line-art and edge-regions segments; full-strength edge step for edge-aware-diffusion
+    #[arg(long, default_value_t = 64.0)]
+    edge_threshold: f32,
+
+    /// How strongly edges stop error in edge-aware-diffusion (0-1)
+    #[arg(long, default_value_t = 1.0)]
+    edge_damping: f32,
+
+    /// Edge sharpening before edge-aware-diffusion; 0 for none
+    #[arg(long, default_value_t = 0.0)]
+    sharpen: f32,
+
+    /// Error diffusion kernel for path-diffusion and edge-aware-diffusion
+    #[arg(long, value_enum, default_value_t = KernelArg::FloydSteinberg)]
+    kernel: KernelArg,
+
+    /// Pixel visiting order for path-diffusion and edge-aware-diffusion
+    #[arg(long, value_enum, default_value_t = ScanPathArg::Serpentine)]
+    scan_path: ScanPathArg,
+
+    /// Riemersma error queue length
+    #[arg(long, default_value_t = 16)]
+    queue_length: usize,
+
+    /// Weight of the oldest Riemersma error relative to the newest
+    #[arg(long, default_value_t = 0.0625)]
+    decay_ratio: f32,
+
+    /// Maximum direct binary search passes
+    #[arg(long, default_value_t = 16)]
+    dbs_iterations: usize,
+
+    /// Stop direct binary search once a pass changes fewer than this fraction of pixels
+    #[arg(long, default_value_t = 0.001)]
+    dbs_min_change: f32,
+
+    /// Neighborhood radius for direct binary search swaps (0 = toggles only)
+    #[arg(long, default_value_t = 1)]
+    dbs_swap_radius: usize,
+
+    /// Palette for dbs-palette as comma-separated hex colors
+    #[arg(long, value_delimiter = ',', value_parser = parse_color, default_value = "000000,ffffff")]
+    palette: Vec<[u8; 3]>,
+
+    /// Eye model for direct binary search
+    #[arg(long, value_enum, default_value_t = HvsArg::Gaussian)]
+    hvs: HvsArg,
+
+    /// Viewing distance in inches for the DBS eye model (with --dpi)
+    #[arg(long, default_value_t = 10.0)]
+    viewing_distance: f32,
+
+    /// GIF frame delay in hundredths of a second (default: the input GIF's delays, else 10)
+    #[arg(long)]
+    delay: Option<u16>,
+
+    /// GIF loop count; 0 loops forever, -1 plays once
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    loop_count: i32,
+
+    /// Largest source change a GIF frame pixel may have and still keep its previous decision
+    #[arg(long, default_value_t = 4)]
+    change_tolerance: u8,
+
+    /// Threshold shift (0-255) towards each pixel's previous decision in GIF frames
+    #[arg(long, default_value_t = 24.0)]
+    hysteresis: f32,
+
+    /// Dither GIF frames without keeping the decisions of unchanged pixels
+    #[arg(long)]
+    independent_frames: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Algorithm {
+    Ordered,
+    ClusteredDot,
+    FloydSteinberg,
+    Atkinson,
+    Halftone,
+    HalftoneScreen,
+    Sobel,
+    Edges,
+    LineArt,
+    Segments,
+    Random,
+    SierraLite,
+    Burkes,
+    Stucki,
+    JarvisJudiceNinke,
+    Pattern,
+    Riemersma,
+    BlueNoise,
+    VoidAndCluster,
+    DirectBinarySearch,
+    DbsLevels,
+    DbsPalette,
+    PathDiffusion,
+    EdgeAwareDiffusion,
+    MultiTone,
+    FmScreen,
+    Rgb,
+    Cmyk,
+    Selective,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Png,
+    Bmp,
+    Pbm,
+    Pgm,
+    Ppm,
+    Svg,
+    Gif,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Bmp => "bmp",
+            Format::Pbm => "pbm",
+            Format::Pgm => "pgm",
+            Format::Ppm => "ppm",
+            Format::Svg => "svg",
+            Format::Gif => "gif",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Format::from_str(&extension, true).ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum PatternArg {
+    Dots,
+    Lines,
+    Crosses,
+    Diamonds,
+    Waves,
+    Bricks,
+    Custom,
+}
+
+impl From<PatternArg> for PatternType {
+    fn from(arg: PatternArg) -> Self {
+        match arg {
+            PatternArg::Dots => PatternType::Dots,
+            PatternArg::Lines => PatternType::Lines,
+            PatternArg::Crosses => PatternType::Crosses,
+            PatternArg::Diamonds => PatternType::Diamonds,
+            PatternArg::Waves => PatternType::Waves,
+            PatternArg::Bricks => PatternType::Bricks,
+            PatternArg::Custom => PatternType::Custom,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ToneAlgorithmArg {
+    Ordered,
+    ErrorDiffusion,
+    BlueNoise,
+}
+
+impl From<ToneAlgorithmArg> for MultiToneAlgorithm {
+    fn from(arg: ToneAlgorithmArg) -> Self {
+        match arg {
+            ToneAlgorithmArg::Ordered => MultiToneAlgorithm::Ordered,
+            ToneAlgorithmArg::ErrorDiffusion => MultiToneAlgorithm::ErrorDiffusion,
+            ToneAlgorithmArg::BlueNoise => MultiToneAlgorithm::BlueNoise,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ShapeArg {
+    Round,
+    Euclidean,
+    Square,
+    Diamond,
+    Ellipse,
+    Line,
+}
+
+impl From<ShapeArg> for DotShape {
+    fn from(arg: ShapeArg) -> Self {
+        match arg {
+            ShapeArg::Round => DotShape::Round,
+            ShapeArg::Euclidean => DotShape::Euclidean,
+            ShapeArg::Square => DotShape::Square,
+            ShapeArg::Diamond => DotShape::Diamond,
+            ShapeArg::Ellipse => DotShape::Ellipse,
+            ShapeArg::Line => DotShape::Line,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum EdgeDetectorArg {
+    Sobel,
+    Scharr,
+    Prewitt,
+    LaplacianOfGaussian,
+    Canny,
+}
+
+impl From<EdgeDetectorArg> for EdgeDetector {
+    fn from(arg: EdgeDetectorArg) -> Self {
+        match arg {
+            EdgeDetectorArg::Sobel => EdgeDetector::Sobel,
+            EdgeDetectorArg::Scharr => EdgeDetector::Scharr,
+            EdgeDetectorArg::Prewitt => EdgeDetector::Prewitt,
+            EdgeDetectorArg::LaplacianOfGaussian => EdgeDetector::LaplacianOfGaussian,
+            EdgeDetectorArg::Canny => EdgeDetector::Canny,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SegmentationArg {
+    Slic,
+    KMeans,
+    EdgeRegions,
+}
+
+impl From<SegmentationArg> for SegmentationMethod {
+    fn from(arg: SegmentationArg) -> Self {
+        match arg {
+            SegmentationArg::Slic => SegmentationMethod::Slic,
+            SegmentationArg::KMeans => SegmentationMethod::KMeans,
+            SegmentationArg::EdgeRegions => SegmentationMethod::EdgeRegions,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum EdgeOutputArg {
+    Binary,
+    Magnitude,
+    Orientation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum KernelArg {
+    FloydSteinberg,
+    Atkinson,
+    SierraLite,
+    Burkes,
+    Stucki,
+    JarvisJudiceNinke,
+}
+
+impl From<KernelArg> for DiffusionKernel {
+    fn from(arg: KernelArg) -> Self {
+        match arg {
+            KernelArg::FloydSteinberg => DiffusionKernel::FloydSteinberg,
+            KernelArg::Atkinson => DiffusionKernel::Atkinson,
+            KernelArg::SierraLite => DiffusionKernel::SierraLite,
+            KernelArg::Burkes => DiffusionKernel::Burkes,
+            KernelArg::Stucki => DiffusionKernel::Stucki,
+            KernelArg::JarvisJudiceNinke => DiffusionKernel::JarvisJudiceNinke,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ScanPathArg {
+    Raster,
+    Serpentine,
+    Hilbert,
+    Gilbert,
+    Peano,
+    ZOrder,
+}
+
+impl From<ScanPathArg> for ScanPath {
+    fn from(arg: ScanPathArg) -> Self {
+        match arg {
+            ScanPathArg::Raster => ScanPath::Raster,
+            ScanPathArg::Serpentine => ScanPath::Serpentine,
+            ScanPathArg::Hilbert => ScanPath::Hilbert,
+            ScanPathArg::Gilbert => ScanPath::Gilbert,
+            ScanPathArg::Peano => ScanPath::Peano,
+            ScanPathArg::ZOrder => ScanPath::ZOrder,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum HvsArg {
+    Gaussian,
+    Nasanen,
+    MannosSakrison,
+}
+
+impl From<HvsArg> for HvsModel {
+    fn from(arg: HvsArg) -> Self {
+        match arg {
+            HvsArg::Gaussian => HvsModel::Gaussian,
+            HvsArg::Nasanen => HvsModel::Nasanen,
+            HvsArg::MannosSakrison => HvsModel::MannosSakrison,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum FmMaskArg {
+    VoidAndCluster,
+    BlueNoise,
+}
+
+impl From<FmMaskArg> for FmMaskSource {
+    fn from(arg: FmMaskArg) -> Self {
+        match arg {
+            FmMaskArg::VoidAndCluster => FmMaskSource::VoidAndCluster,
+            FmMaskArg::BlueNoise => FmMaskSource::BlueNoise,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SeparationArg {
+    Ucr,
+    Gcr,
+}
+
+impl From<SeparationArg> for SeparationMode {
+    fn from(arg: SeparationArg) -> Self {
+        match arg {
+            SeparationArg::Ucr => SeparationMode::Ucr,
+            SeparationArg::Gcr => SeparationMode::Gcr,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ChannelAlgorithmArg {
+    Ordered,
+    FloydSteinberg,
+    Atkinson,
+    Halftone,
+    Random,
+    SierraLite,
+    Burkes,
+    Stucki,
+    JarvisJudiceNinke,
+    Pattern,
+    Riemersma,
+    BlueNoise,
+    VoidAndCluster,
+    DirectBinarySearch,
+    MultiTone,
+}
+
+impl From<ChannelAlgorithmArg> for DitheringAlgorithmType {
+    fn from(arg: ChannelAlgorithmArg) -> Self {
+        match arg {
+            ChannelAlgorithmArg::Ordered => DitheringAlgorithmType::Ordered,
+            ChannelAlgorithmArg::FloydSteinberg => DitheringAlgorithmType::FloydSteinberg,
+            ChannelAlgorithmArg::Atkinson => DitheringAlgorithmType::Atkinson,
+            ChannelAlgorithmArg::Halftone => DitheringAlgorithmType::Halftone,
+            ChannelAlgorithmArg::Random => DitheringAlgorithmType::Random,
+            ChannelAlgorithmArg::SierraLite => DitheringAlgorithmType::SierraLite,
+            ChannelAlgorithmArg::Burkes => DitheringAlgorithmType::Burkes,
+            ChannelAlgorithmArg::Stucki => DitheringAlgorithmType::Stucki,
+            ChannelAlgorithmArg::JarvisJudiceNinke => DitheringAlgorithmType::JarvisJudiceNinke,
+            ChannelAlgorithmArg::Pattern => DitheringAlgorithmType::Pattern,
+            ChannelAlgorithmArg::Riemersma => DitheringAlgorithmType::Riemersma,
+            ChannelAlgorithmArg::BlueNoise => DitheringAlgorithmType::BlueNoise,
+            ChannelAlgorithmArg::VoidAndCluster => DitheringAlgorithmType::VoidAndCluster,
+            ChannelAlgorithmArg::DirectBinarySearch => DitheringAlgorithmType::DirectBinarySearch,
+            ChannelAlgorithmArg::MultiTone => DitheringAlgorithmType::MultiTone,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SvgModeArg {
+    Runs,
+    Rects,
+    Paths,
+}
+
+impl From<SvgModeArg> for SvgTraceMode {
+    fn from(arg: SvgModeArg) -> Self {
+        match arg {
+            SvgModeArg::Runs => SvgTraceMode::Runs,
+            SvgModeArg::Rects => SvgTraceMode::Rects,
+            SvgModeArg::Paths => SvgTraceMode::Paths,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SvgUnitArg {
+    Px,
+    Mm,
+    In,
+    Pt,
+}
+
+impl From<SvgUnitArg> for SvgUnit {
+    fn from(arg: SvgUnitArg) -> Self {
+        match arg {
+            SvgUnitArg::Px => SvgUnit::Px,
+            SvgUnitArg::Mm => SvgUnit::Mm,
+            SvgUnitArg::In => SvgUnit::In,
+            SvgUnitArg::Pt => SvgUnit::Pt,
+        }
+    }
+}
+
+// Parse a hex color like "ff8800" or "#ff8800"
+fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value.trim().trim_start_matches('#');
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("expected a hex color like ff8800, got `{value}`")),
+    }
+}
+
+// Dithered result: `channels` bytes per pixel, or the four CMYK plates
+enum Rendered {
+    Pixels { data: Vec<u8>, channels: usize },
+    Plates(Vec<u8>),
+}
+
+// Run the selected algorithm on a decoded image; `masks` are the selective regions
+fn render(cli: &Cli, image: &Image, masks: &[Vec<u8>]) -> Rendered {
+    let (width, height) = (image.width, image.height);
+    let size = width * height;
+    let gray = image.grayscale();
+    let input = gray.as_ptr();
+
+    // One-byte and RGBA outputs follow the conventions of each exported function
+    let mut one = vec![0u8; size];
+    let mut four = vec![0u8; size * 4];
+    let out1 = one.as_mut_ptr();
+    let out4 = four.as_mut_ptr();
+
+    let channels = match cli.algorithm {
+        Algorithm::Ordered => { ordered_dither(input, width, height, cli.dot_size, out1); 1 }
+        Algorithm::ClusteredDot => {
+            clustered_dot_dither(input, width, height, cli.lpi, cli.angle, cli.dpi, cli.shape.into(), out1);
+            1
+        }
+        Algorithm::FloydSteinberg => { floyd_steinberg_dither(input, width, height, cli.threshold, out1); 1 }
+        Algorithm::Atkinson => { atkinson_dither(input, width, height, cli.threshold, out1); 1 }
+        Algorithm::Halftone => {
+            halftone_dither(input, width, height, cli.dot_size, cli.spacing, cli.angle, out1);
+            1
+        }
+        Algorithm::HalftoneScreen => {
+            halftone_screen_dither(input, width, height, cli.lpi, cli.dpi, cli.angle, out1);
+            1
+        }
+        Algorithm::Sobel => { sobel_edge_detection(input, width, height, cli.threshold, out1); 1 }
+        Algorithm::Edges => {
+            let mut magnitude = vec![0.0f32; width * height];
+            let mut orientation = vec![0.0f32; width * height];
+            detect_edges(
+                input, width, height, cli.edge_detector.into(), cli.threshold as f32, cli.low_threshold, cli.sigma,
+                out1, magnitude.as_mut_ptr(), orientation.as_mut_ptr(),
+            );
+            // Gradient maps as gray: magnitude clamped to 255, orientation from -pi (black) to pi (white)
+            match cli.edge_output {
+                EdgeOutputArg::Binary => {}
+                EdgeOutputArg::Magnitude => {
+                    one.iter_mut().zip(&magnitude).for_each(|(o, &m)| *o = m.round().min(255.0) as u8);
+                }
+                EdgeOutputArg::Orientation => {
+                    let scale = 255.0 / (2.0 * std::f32::consts::PI);
+                    one.iter_mut().zip(&orientation).for_each(|(o, &a)| *o = ((a + std::f32::consts::PI) * scale).round() as u8);
+                }
+            }
+            1
+        }
+        Algorithm::LineArt => {
+            line_art_dither(
+                input, width, height, cli.fill.into(), cli.threshold, cli.dot_size, cli.noise,
+                cli.edge_detector.into(), cli.edge_threshold, cli.low_threshold, cli.sigma, cli.stroke_width, out1,
+            );
+            1
+        }
+        Algorithm::Segments => {
+            let mut labels = vec![0u32; size];
+            let count = segment_image(
+                image.rgba.as_ptr(), width, height, cli.segmentation.into(), cli.regions, cli.compactness,
+                cli.min_region_size, cli.edge_detector.into(), cli.edge_threshold, cli.low_threshold, cli.sigma,
+                cli.seed, labels.as_mut_ptr(),
+            );
+            // Preview: every region painted in its mean color
+            let mut sums = vec![[0u64; 4]; count];
+            for (&label, pixel) in labels.iter().zip(image.rgba.chunks_exact(4)) {
+                let sum = &mut sums[label as usize];
+                for c in 0..3 {
+                    sum[c] += pixel[c] as u64;
+                }
+                sum[3] += 1;
+            }
+            for (&label, out) in labels.iter().zip(four.chunks_exact_mut(4)) {
+                let sum = sums[label as usize];
+                for c in 0..3 {
+                    out[c] = (sum[c] / sum[3].max(1)) as u8;
+                }
+                out[3] = 255;
+            }
+            4
+        }
+        Algorithm::Random => { random_dither(input, width, height, cli.threshold, cli.noise, out4); 4 }
+        Algorithm::SierraLite => { sierra_lite_dither(input, width, height, cli.threshold, out4); 4 }
+        Algorithm::Burkes => { burkes_dither(input, width, height, cli.threshold, out4); 4 }
+        Algorithm::Stucki => { stucki_dither(input, width, height, cli.threshold, out4); 4 }
+        Algorithm::JarvisJudiceNinke => { jarvis_judice_ninke_dither(input, width, height, cli.threshold, out4); 4 }
+        Algorithm::Pattern => {
+            pattern_dither(input, width, height, cli.pattern.into(), cli.pattern_size, out4);
+            4
+        }
+        Algorithm::Riemersma => {
+            riemersma_queue_dither(input, width, height, cli.threshold, cli.queue_length, cli.decay_ratio, out1);
+            1
+        }
+        Algorithm::BlueNoise => {
+            blue_noise_mask_dither(input, width, height, cli.mask_size, cli.seed, cli.threshold, out1);
+            1
+        }
+        Algorithm::VoidAndCluster => { void_and_cluster_dither(input, width, height, cli.threshold, out4); 4 }
+        Algorithm::DirectBinarySearch => {
+            dbs_dither(
+                input, width, height, cli.threshold, cli.dbs_iterations,
+                cli.dbs_min_change, cli.dbs_swap_radius, cli.hvs.into(), cli.viewing_distance, cli.dpi, out1,
+            );
+            1
+        }
+        Algorithm::DbsLevels => {
+            dbs_levels_dither(
+                input, width, height, cli.levels, cli.dbs_iterations,
+                cli.dbs_min_change, cli.dbs_swap_radius, cli.hvs.into(), cli.viewing_distance, cli.dpi, out1,
+            );
+            1
+        }
+        Algorithm::DbsPalette => {
+            let palette: Vec<u8> = cli.palette.iter().flatten().copied().collect();
+            dbs_palette_dither(
+                image.rgba.as_ptr(), width, height, palette.as_ptr(), cli.palette.len(), cli.dbs_iterations,
+                cli.dbs_min_change, cli.dbs_swap_radius, cli.hvs.into(), cli.viewing_distance, cli.dpi, out4,
+            );
+            4
+        }
+        Algorithm::PathDiffusion => {
+            path_diffusion_dither(input, width, height, cli.kernel.into(), cli.scan_path.into(), cli.threshold, out1);
+            1
+        }
+        Algorithm::EdgeAwareDiffusion => {
+            edge_aware_diffusion_dither(
+                input, width, height, cli.kernel.into(), cli.scan_path.into(), cli.threshold,
+                cli.edge_detector.into(), cli.sigma, cli.edge_threshold, cli.edge_damping, cli.sharpen, out1,
+            );
+            1
+        }
+        Algorithm::MultiTone => {
+            multi_tone_dither(input, width, height, cli.levels, cli.tone_algorithm.into(), cli.dot_size, out4);
+            4
+        }
+        Algorithm::FmScreen => {
+            fm_screen_dither(input, width, height, cli.dot_size, cli.fm_mask.into(), out1);
+            1
+        }
+        Algorithm::Rgb => {
+            rgb_dither(
+                image.rgba.as_ptr(), width, height, cli.channel_algorithm.into(), cli.levels,
+                cli.threshold, cli.dot_size, cli.spacing, cli.angle, cli.noise, cli.pattern.into(),
+                cli.pattern_size, cli.levels, cli.tone_algorithm.into(), cli.decorrelate, out4,
+            );
+            4
+        }
+        Algorithm::Cmyk => {
+            // Plates and the RGBA preview both take four bytes per pixel
+            let shapes = [DotShape::from(cli.shape) as u32; 4];
+            let output = if cli.plates { CmykOutput::Plates } else { CmykOutput::Preview };
+            cmyk_halftone(
+                image.rgba.as_ptr(), width, height, cli.lpi, cli.dpi,
+                std::ptr::null(), shapes.as_ptr(),
+                cli.separation.into(), cli.black_generation, cli.black_start,
+                cli.under_color_removal, cli.ink_limit,
+                output, out4,
+            );
+            if cli.plates {
+                return Rendered::Plates(four);
+            }
+            4
+        }
+        Algorithm::Selective => {
+            // Every region uses the global parameters with its own algorithm
+            let count = masks.len();
+            let mask_pointers: Vec<*const u8> = masks.iter().map(|mask| mask.as_ptr()).collect();
+            let algorithms: Vec<u32> = (0..count)
+                .map(|i| {
+                    let algorithm = cli.mask_algorithms.get(i).or(cli.mask_algorithms.last()).copied();
+                    DitheringAlgorithmType::from(algorithm.unwrap_or(ChannelAlgorithmArg::FloydSteinberg)) as u32
+                })
+                .collect();
+            let pattern_type: PatternType = cli.pattern.into();
+            let tone_algorithm: MultiToneAlgorithm = cli.tone_algorithm.into();
+            selective_dither(
+                input, width, height, mask_pointers.as_ptr(), algorithms.as_ptr(),
+                vec![cli.threshold; count].as_ptr(), vec![cli.dot_size; count].as_ptr(),
+                vec![cli.spacing; count].as_ptr(), vec![cli.angle; count].as_ptr(), vec![cli.noise; count].as_ptr(),
+                vec![pattern_type as u32; count].as_ptr(), vec![cli.pattern_size; count].as_ptr(),
+                vec![cli.levels; count].as_ptr(), vec![tone_algorithm as u32; count].as_ptr(), count,
+                cli.fill.into(), cli.threshold, cli.dot_size, cli.spacing, cli.angle, cli.noise,
+                pattern_type, cli.pattern_size, cli.levels, tone_algorithm, out4,
+            );
+            4
+        }
+    };
+
+    match channels {
+        1 => Rendered::Pixels { data: one, channels: 1 },
+        _ => Rendered::Pixels { data: four, channels: 4 },
+    }
+}
+
+// Encode pixels in the requested format
+fn encode(
+    cli: &Cli,
+    format: Format,
+    data: &[u8],
+    channels: usize,
+    width: usize,
+    height: usize,
+    gray_input: &[u8],
+) -> Result<Vec<u8>, String> {
+    let ptr = data.as_ptr();
+    let encoded = match format {
+        Format::Png => encode_png(ptr, width, height, channels, PngColorMode::Auto),
+        Format::Bmp => encode_bmp(ptr, width, height, channels),
+        Format::Pbm => encode_netpbm(ptr, width, height, channels, NetpbmFormat::Pbm),
+        Format::Pgm => encode_netpbm(ptr, width, height, channels, NetpbmFormat::Pgm),
+        Format::Ppm => encode_netpbm(ptr, width, height, channels, NetpbmFormat::Ppm),
+        Format::Svg => {
+            let unit = cli.svg_unit.into();
+            let svg = match cli.algorithm {
+                // Exact vector geometry instead of traced pixels
+                Algorithm::Halftone => halftone_svg(
+                    gray_input.as_ptr(), width, height, cli.dot_size, cli.spacing, cli.angle, unit, cli.dpi, true,
+                ),
+                Algorithm::Pattern => pattern_svg(
+                    gray_input.as_ptr(), width, height, cli.pattern.into(), cli.pattern_size, unit, cli.dpi, true,
+                ),
+                _ => {
+                    let plane: Vec<u8> = data.iter().step_by(channels).copied().collect();
+                    let is_binary = data
+                        .chunks_exact(channels)
+                        .all(|p| p.iter().take(3).all(|&v| v == p[0]) && (p[0] == 0 || p[0] == 255));
+                    if !is_binary {
+                        return Err("SVG output needs a black and white result".to_string());
+                    }
+                    binary_to_svg(
+                        plane.as_ptr(), width, height, cli.svg_mode.into(), cli.despeckle, unit, cli.dpi, true,
+                    )
+                    .svg()
+                }
+            };
+            svg.into_bytes()
+        }
+        Format::Gif => return Err("GIF output needs the whole animation".to_string()),
+    };
+    if encoded.is_empty() {
+        return Err(format!("the result has more than 256 colors, which {} cannot store", format.extension()));
+    }
+    Ok(encoded)
+}
+
+// Algorithms the animation ditherer can run, which are those selective offers
+fn animation_algorithm(algorithm: Algorithm) -> Option<DitheringAlgorithmType> {
+    let algorithm = match algorithm {
+        Algorithm::Ordered => DitheringAlgorithmType::Ordered,
+        Algorithm::FloydSteinberg => DitheringAlgorithmType::FloydSteinberg,
+        Algorithm::Atkinson => DitheringAlgorithmType::Atkinson,
+        Algorithm::Halftone => DitheringAlgorithmType::Halftone,
+        Algorithm::Random => DitheringAlgorithmType::Random,
+        Algorithm::SierraLite => DitheringAlgorithmType::SierraLite,
+        Algorithm::Burkes => DitheringAlgorithmType::Burkes,
+        Algorithm::Stucki => DitheringAlgorithmType::Stucki,
+        Algorithm::JarvisJudiceNinke => DitheringAlgorithmType::JarvisJudiceNinke,
+        Algorithm::Pattern => DitheringAlgorithmType::Pattern,
+        Algorithm::Riemersma => DitheringAlgorithmType::Riemersma,
+        Algorithm::BlueNoise => DitheringAlgorithmType::BlueNoise,
+        Algorithm::VoidAndCluster => DitheringAlgorithmType::VoidAndCluster,
+        Algorithm::DirectBinarySearch => DitheringAlgorithmType::DirectBinarySearch,
+        Algorithm::MultiTone => DitheringAlgorithmType::MultiTone,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+// Dither the frames of all inputs as one animation and write it as a GIF.
+// Returns the output path and the number of frames.
+fn animate(cli: &Cli, inputs: &[PathBuf]) -> Result<(PathBuf, usize), String> {
+    let Some(algorithm) = animation_algorithm(cli.algorithm) else {
+        let name = cli.algorithm.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+        return Err(format!("{} cannot be animated; GIF output takes the algorithms selective offers", name));
+    };
+
+    let mut frames = Vec::new();
+    for input in inputs {
+        frames.extend(load_frames(input).map_err(|err| format!("{}: {}", input.display(), err))?);
+    }
+    let Some(first) = frames.first() else { return Err("no frames to animate".to_string()) };
+    let (width, height) = (first.image.width, first.image.height);
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(format!("{}x{} is too large for a GIF", width, height));
+    }
+    if let Some(i) = frames.iter().position(|f| (f.image.width, f.image.height) != (width, height)) {
+        let image = &frames[i].image;
+        return Err(format!("frame {} is {}x{}, the first frame is {}x{}", i + 1, image.width, image.height, width, height));
+    }
+
+    let params = AlgorithmParams {
+        threshold: cli.threshold,
+        dot_size: cli.dot_size,
+        spacing: cli.spacing,
+        angle: cli.angle,
+        noise_amount: cli.noise,
+        pattern_type: cli.pattern.into(),
+        pattern_size: cli.pattern_size,
+        multi_tone_levels: cli.levels,
+        multi_tone_algo: cli.tone_algorithm.into(),
+    };
+    let options = TemporalOptions {
+        seed: cli.seed as u64,
+        reuse_unchanged: !cli.independent_frames,
+        change_tolerance: cli.change_tolerance,
+        hysteresis: cli.hysteresis,
+    };
+    let mut ditherer = AnimationDitherer::with_options(width, height, algorithm, params, options);
+
+    // Black and white, or the gray steps of multi-tone
+    let colors = if algorithm == DitheringAlgorithmType::MultiTone { cli.levels.clamp(2, 256) } else { 2 };
+    let palette: Vec<u8> = (0..colors).flat_map(|i| [(i * 255 / (colors - 1)) as u8; 3]).collect();
+    let mut encoder = GifEncoder::new(width as u16, height as u16, palette.as_ptr(), colors, cli.loop_count);
+    for frame in &frames {
+        let dithered = ditherer.dither(&frame.image.grayscale());
+        let delay = cli.delay.or(frame.delay).unwrap_or(10);
+        encoder.add_dithered_frame(dithered.as_ptr(), delay, GifDisposal::Unspecified);
+    }
+
+    let path = output_path(cli, &inputs[0], Format::Gif, true, "");
+    std::fs::write(&path, encoder.finish()).map_err(|err| format!("could not write {}: {}", path.display(), err))?;
+    Ok((path, frames.len()))
+}
+
+// Expand glob patterns; plain paths are kept even when they do not exist so the error names them
+fn expand_inputs(patterns: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        let matches: Vec<PathBuf> = glob::glob(pattern)
+            .map_err(|err| format!("invalid pattern {}: {}", pattern, err))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect();
+        if matches.is_empty() {
+            inputs.push(PathBuf::from(pattern));
+        } else {
+            inputs.extend(matches);
+        }
+    }
+    Ok(inputs)
+}
+
+// Output path for one input. A single input goes to the -o file (plates get a
+// suffix before the extension); otherwise files land in the -o directory (or
+// next to the input) as <stem>-<algorithm>[suffix].<ext>, never over the input.
+fn output_path(cli: &Cli, input: &Path, format: Format, single: bool, suffix: &str) -> PathBuf {
+    if let Some(output) = cli.output.as_ref().filter(|o| single && !o.is_dir()) {
+        if suffix.is_empty() {
+            return output.clone();
+        }
+        let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+        return output.with_file_name(format!("{}{}.{}", stem, suffix, format.extension()));
+    }
+
+    let directory = match &cli.output {
+        Some(output) => output.clone(),
+        None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let algorithm = cli.algorithm.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+    directory.join(format!("{}-{}{}.{}", stem, algorithm, suffix, format.extension()))
+}
+
+// Grayscale --mask images for selective, which must match the input size
+fn load_masks(cli: &Cli, width: usize, height: usize) -> Result<Vec<Vec<u8>>, String> {
+    if cli.algorithm != Algorithm::Selective { return Ok(Vec::new()); }
+    cli.masks
+        .iter()
+        .map(|path| {
+            let mask = load_image(path).map_err(|err| err.to_string())?;
+            if (mask.width, mask.height) != (width, height) {
+                return Err(format!(
+                    "mask {} is {}x{}, the image is {}x{}",
+                    path.display(), mask.width, mask.height, width, height,
+                ));
+            }
+            Ok(mask.grayscale())
+        })
+        .collect()
+}
+
+fn process(cli: &Cli, input: &Path, format: Format, single: bool) -> Result<Vec<PathBuf>, String> {
+    let image = load_image(input).map_err(|err| err.to_string())?;
+    let (width, height) = (image.width, image.height);
+    let gray = image.grayscale();
+
+    let mut written = Vec::new();
+    let mut write = |suffix: &str, bytes: Vec<u8>| -> Result<(), String> {
+        let path = output_path(cli, input, format, single, suffix);
+        std::fs::write(&path, bytes).map_err(|err| format!("could not write {}: {}", path.display(), err))?;
+        written.push(path);
+        Ok(())
+    };
+
+    let masks = load_masks(cli, width, height)?;
+    match render(cli, &image, &masks) {
+        Rendered::Pixels { data, channels } => {
+            write("", encode(cli, format, &data, channels, width, height, &gray)?)?;
+        }
+        Rendered::Plates(planes) => {
+            let size = width * height;
+            for (plate, suffix) in ["-c", "-m", "-y", "-k"].iter().enumerate() {
+                let data = &planes[plate * size..(plate + 1) * size];
+                write(suffix, encode(cli, format, data, 1, width, height, &gray)?)?;
+            }
+        }
+    }
+    Ok(written)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let inputs = match expand_inputs(&cli.inputs) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("dither: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Every input is a frame of the same animation, so -o names one file
+    let output_format = cli.output.as_deref().and_then(Format::from_path);
+    if cli.format.or(output_format) == Some(Format::Gif) {
+        return match animate(&cli, &inputs) {
+            Ok((path, count)) => {
+                println!("{} frames -> {}", count, path.display());
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("dither: {}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let single = inputs.len() == 1;
+    if !single {
+        if let Some(output) = &cli.output {
+            if let Err(err) = std::fs::create_dir_all(output) {
+                eprintln!("dither: could not create {}: {}", output.display(), err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let format = cli
+        .format
+        .or_else(|| cli.output.as_deref().filter(|_| single).and_then(Format::from_path))
+        .unwrap_or(Format::Png);
+
+    // Keep going through a batch and report every failure at the end
+    let mut failures = 0;
+    for input in &inputs {
+        match process(&cli, input, format, single) {
+            Ok(paths) => {
+                for path in paths {
+                    println!("{} -> {}", input.display(), path.display());
+                }
+            }
+            Err(err) => {
+                eprintln!("dither: {}: {}", input.display(), err);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
@@ -1,104 +1,55 @@
-use rand::Rng;
-
-// Generate a tiled blue noise pattern using a simplified Mitchell's best candidate algorithm
-fn generate_blue_noise_pattern(width: usize, height: usize) -> Vec<Vec<f32>> {
-    let pattern_size = 64; // Power of 2 is common
-    let pattern_size_f = pattern_size as f32;
-    let mut rng = rand::thread_rng();
-
-    // --- 1. Generate Blue Noise Points using Mitchell's Best Candidate --- 
-    let num_points = (pattern_size * pattern_size) / 10; // ~10% density
-    let candidates_per_point = 20; // More candidates = better quality but slower
-    let mut points: Vec<(usize, usize)> = Vec::with_capacity(num_points);
-    let mut point_map = vec![vec![false; pattern_size]; pattern_size]; // Faster lookup
-
-    // Place the first point
-    if num_points > 0 {
-        let first_x = rng.gen_range(0..pattern_size);
-        let first_y = rng.gen_range(0..pattern_size);
-        points.push((first_x, first_y));
-        point_map[first_y][first_x] = true;
-    }
-
-    // Place remaining points
-    for _ in 1..num_points {
-        let mut best_candidate: Option<(usize, usize)> = None;
-        let mut best_dist_sq = 0.0f32;
-
-        for _ in 0..candidates_per_point {
-            let cand_x = rng.gen_range(0..pattern_size);
-            let cand_y = rng.gen_range(0..pattern_size);
-
-            if point_map[cand_y][cand_x] { continue; } // Already occupied
-
-            let mut min_dist_sq = f32::MAX;
-
-            // Find distance to nearest existing point (toroidal)
-            for &(px, py) in &points {
-                let dx = (cand_x as f32 - px as f32).abs();
-                let dy = (cand_y as f32 - py as f32).abs();
-                
-                // Toroidal distance calculation
-                let toroidal_dx = dx.min(pattern_size_f - dx);
-                let toroidal_dy = dy.min(pattern_size_f - dy);
-                
-                let dist_sq = toroidal_dx * toroidal_dx + toroidal_dy * toroidal_dy;
-                min_dist_sq = min_dist_sq.min(dist_sq);
-            }
-
-            // If this candidate is farther from existing points than the current best
-            if min_dist_sq > best_dist_sq {
-                best_dist_sq = min_dist_sq;
-                best_candidate = Some((cand_x, cand_y));
-            }
-        }
-
-        if let Some((bx, by)) = best_candidate {
-            points.push((bx, by));
-            point_map[by][bx] = true;
-        }
-    }
+use std::rc::Rc;
+use crate::void_and_cluster::void_and_cluster_mask;
+
+// Blue-noise threshold masks
+//
+// Masks come from the void-and-cluster generator (see void_and_cluster.rs).
+// Each rank is used exactly once, so thresholding the mask at any level turns
+// on that fraction of pixels and the histogram is uniform. Masks tile
+// seamlessly (toroidal distances) and are cached per size and seed.
+
+// Mask size used when tiling over an image
+pub const DEFAULT_MASK_SIZE: usize = 64;
+
+// Blue-noise threshold mask, size x size values in (0, 1), each (rank + 0.5) / size^2
+pub fn blue_noise_mask(size: usize, seed: u64) -> Rc<Vec<f32>> {
+    void_and_cluster_mask(size, seed)
+}
 
-    // --- 2. Create Distance Field --- 
-    let mut distance_field = vec![vec![f32::MAX; pattern_size]; pattern_size];
-    let mut max_distance = 0.0f32;
-
-    for y in 0..pattern_size {
-        for x in 0..pattern_size {
-            let mut min_dist_sq = f32::MAX;
-            for &(px, py) in &points {
-                 let dx = (x as f32 - px as f32).abs();
-                 let dy = (y as f32 - py as f32).abs();
-                 let toroidal_dx = dx.min(pattern_size_f - dx);
-                 let toroidal_dy = dy.min(pattern_size_f - dy);
-                 let dist_sq = toroidal_dx * toroidal_dx + toroidal_dy * toroidal_dy;
-                 min_dist_sq = min_dist_sq.min(dist_sq);
-            }
-            let distance = min_dist_sq.sqrt();
-            distance_field[y][x] = distance;
-            max_distance = max_distance.max(distance);
-        }
-    }
+// Default blue-noise mask tiled over width x height, values in (0, 1)
+pub fn generate_blue_noise_pattern(width: usize, height: usize) -> Vec<Vec<f32>> {
+    let mask = blue_noise_mask(DEFAULT_MASK_SIZE, 0);
+    (0..height)
+        .map(|y| {
+            let row = (y % DEFAULT_MASK_SIZE) * DEFAULT_MASK_SIZE;
+            (0..width).map(|x| mask[row + x % DEFAULT_MASK_SIZE]).collect()
+        })
+        .collect()
+}
 
-    // --- 3. Normalize Distance Field --- 
-    let mut normalized_field = vec![vec![0.0f32; pattern_size]; pattern_size];
-    if max_distance > 0.0 {
-        for y in 0..pattern_size {
-            for x in 0..pattern_size {
-                normalized_field[y][x] = distance_field[y][x] / max_distance;
-            }
-        }
-    }
+// Gray level below which a pixel turns black for a mask value; threshold 128 is the plain mask
+pub fn mask_threshold(mask_value: f32, threshold: u8) -> f32 {
+    mask_value * 255.0 + (threshold as f32 - 128.0)
+}
 
-    // --- 4. Tile the Normalized Pattern --- 
-    let mut tiled_pattern = vec![vec![0.0f32; width]; height];
+// Threshold a grayscale plane against a tiled mask; one byte per pixel output
+pub fn mask_dither(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    mask: &[f32],
+    mask_size: usize,
+    threshold: u8,
+    output: &mut [u8],
+) {
     for y in 0..height {
+        let mask_row = (y % mask_size) * mask_size;
         for x in 0..width {
-            tiled_pattern[y][x] = normalized_field[y % pattern_size][x % pattern_size];
+            let idx = y * width + x;
+            let limit = mask_threshold(mask[mask_row + x % mask_size], threshold);
+            output[idx] = if (input[idx] as f32) < limit { 0 } else { 255 };
         }
     }
-
-    tiled_pattern
 }
 
 pub fn blue_noise_dither(
@@ -111,33 +62,131 @@ pub fn blue_noise_dither(
     let size = width * height;
     let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
     let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) }; // RGBA output
-    let threshold_f = threshold as f32;
 
-    // Generate the blue noise pattern (tiled to image dimensions)
-    let noise_pattern = generate_blue_noise_pattern(width, height);
+    let mask = blue_noise_mask(DEFAULT_MASK_SIZE, 0);
+    let mut plane = vec![0u8; size];
+    mask_dither(input, width, height, &mask, DEFAULT_MASK_SIZE, threshold, &mut plane);
 
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let pixel = input[idx] as f32;
+    // Write RGBA output pixels
+    for (i, &value) in plane.iter().enumerate() {
+        output[i * 4] = value;
+        output[i * 4 + 1] = value;
+        output[i * 4 + 2] = value;
+        output[i * 4 + 3] = 255; // Alpha
+    }
+}
+
+// Blue-noise dithering with a chosen mask size and seed; one byte per pixel output
+pub fn blue_noise_mask_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    mask_size: usize,
+    seed: u64,
+    threshold: u8,
+    output_ptr: *mut u8,
+) {
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+
+    let mask_size = mask_size.max(1);
+    let mask = blue_noise_mask(mask_size, seed);
+    mask_dither(input, width, height, &mask, mask_size, threshold, output);
+}
 
-            // Get noise value (0.0 to 1.0)
-            let noise_value = noise_pattern[y][x]; 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::void_and_cluster::void_and_cluster_ranks;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Radially averaged power spectrum of a binary pattern (naive DFT), indexed by integer radius
+    fn radial_power(pattern: &[bool], size: usize) -> Vec<f32> {
+        let mean = pattern.iter().filter(|&&p| p).count() as f32 / pattern.len() as f32;
+        let mut sums = vec![0.0f32; size];
+        let mut counts = vec![0usize; size];
+        for v in 0..size {
+            for u in 0..size {
+                let (mut re, mut im) = (0.0f32, 0.0f32);
+                for y in 0..size {
+                    for x in 0..size {
+                        let value = pattern[y * size + x] as u8 as f32 - mean;
+                        let phase = -2.0 * std::f32::consts::PI * ((u * x + v * y) % size) as f32 / size as f32;
+                        re += value * phase.cos();
+                        im += value * phase.sin();
+                    }
+                }
+                // Signed frequencies so the radius is measured from DC
+                let fu = if u > size / 2 { size - u } else { u } as f32;
+                let fv = if v > size / 2 { size - v } else { v } as f32;
+                let radius = (fu * fu + fv * fv).sqrt().round() as usize;
+                if radius < size {
+                    sums[radius] += re * re + im * im;
+                    counts[radius] += 1;
+                }
+            }
+        }
+        sums.iter().zip(counts.iter()).map(|(&s, &c)| if c > 0 { s / c as f32 } else { 0.0 }).collect()
+    }
 
-            // Apply blue noise: Add noise scaled to -128..127 range (approx)
-            // Adjust the formula from TS slightly as noise_value is 0-1 here
-            let noised_pixel = pixel + (noise_value * 255.0 - 128.0) * 0.5; // Strength factor 0.5
+    // Mean power below and above a quarter of the maximum radial frequency
+    fn low_high_ratio(mask: &[f32], size: usize, level: f32) -> f32 {
+        let pattern: Vec<bool> = mask.iter().map(|&m| m < level).collect();
+        let power = radial_power(&pattern, size);
+        let low: f32 = power[1..size / 8].iter().sum::<f32>() / (size / 8 - 1) as f32;
+        let high: f32 = power[size / 4..size / 2].iter().sum::<f32>() / (size / 4) as f32;
+        low / high
+    }
 
-            // Clamp and apply threshold
-            let clamped_pixel = noised_pixel.max(0.0).min(255.0);
-            let new_pixel_value = if clamped_pixel < threshold_f { 0 } else { 255 };
+    #[test]
+    fn histogram_is_uniform() {
+        let size = 32;
+        let mask = blue_noise_mask(size, 7);
+        let mut ranks: Vec<usize> = mask.iter().map(|&m| (m * (size * size) as f32) as usize).collect();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+    }
+
+    #[test]
+    fn seed_is_deterministic() {
+        let a = void_and_cluster_ranks(16, 3);
+        assert_eq!(a, void_and_cluster_ranks(16, 3));
+        assert_ne!(a, void_and_cluster_ranks(16, 4));
+    }
 
-            // Write RGBA output pixel
-            let base_idx_out = idx * 4;
-            output[base_idx_out] = new_pixel_value;
-            output[base_idx_out + 1] = new_pixel_value;
-            output[base_idx_out + 2] = new_pixel_value;
-            output[base_idx_out + 3] = 255; // Alpha
+    #[test]
+    fn low_frequencies_are_suppressed() {
+        let size = 32;
+        let mask = blue_noise_mask(size, 1);
+        // White noise has a flat spectrum (ratio near 1); blue noise has almost no low-frequency power
+        for level in [0.1, 0.25, 0.5, 0.75] {
+            let ratio = low_high_ratio(&mask, size, level);
+            assert!(ratio < 0.2, "low/high power ratio {} at level {}", ratio, level);
+        }
+    }
+
+    #[test]
+    fn white_noise_fails_the_spectral_check() {
+        // Sanity check of the measurement itself
+        let size = 32;
+        let mut rng = StdRng::seed_from_u64(5);
+        let noise: Vec<f32> = (0..size * size).map(|_| rng.gen::<f32>()).collect();
+        assert!(low_high_ratio(&noise, size, 0.5) > 0.5);
+    }
+
+    #[test]
+    fn no_adjacent_points_at_low_density() {
+        // At 10% coverage void-and-cluster keeps every dot isolated
+        let size = 32;
+        let mask = blue_noise_mask(size, 2);
+        let on: Vec<bool> = mask.iter().map(|&m| m < 0.1).collect();
+        for y in 0..size {
+            for x in 0..size {
+                if !on[y * size + x] { continue; }
+                assert!(!on[y * size + (x + 1) % size] && !on[((y + 1) % size) * size + x]);
+            }
         }
     }
 }
@@ -0,0 +1,274 @@
+use wasm_bindgen::prelude::*;
+
+use crate::ordered::threshold_array_dither;
+use crate::supercell::{generate_threshold_array, DotShape};
+
+// CMYK separation with one halftone screen per plate
+//
+// RGB is converted to CMY, the gray component shared by all three inks is
+// (partly) replaced by black, and every plate is screened with its own angle
+// and dot shape using the supercell threshold arrays. The result is either the
+// four 1-bit plates or an RGBA preview that multiplies the inks on paper the
+// way overprinted process inks do.
+
+// How under-color is removed once black has been generated
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeparationMode {
+    // Under Color Removal: only neutral (gray) areas lose CMY under the black
+    Ucr,
+    // Gray Component Replacement: the gray component is replaced in every color
+    Gcr,
+}
+
+// What cmyk_halftone writes to its output buffer
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmykOutput {
+    // Four width*height planes in C, M, Y, K order; 0 = ink, 255 = paper
+    Plates,
+    // One RGBA image simulating the overprinted inks
+    Preview,
+}
+
+// Separation settings
+#[derive(Clone, Copy, Debug)]
+pub struct SeparationOptions {
+    pub mode: SeparationMode,
+    pub black_generation: f32,    // 0..1, share of the gray component printed as black
+    pub black_start: f32,         // 0..1, gray level below which no black is generated
+    pub under_color_removal: f32, // 0..1, share of the black removed from C, M and Y
+    pub ink_limit: f32,           // Maximum total coverage, e.g. 3.0 for 300%
+}
+
+// Screen used for one plate
+#[derive(Clone, Copy, Debug)]
+pub struct PlateScreen {
+    pub angle: f32,
+    pub shape: DotShape,
+}
+
+// Conventional plate angles: C 15, M 75, Y 0, K 45
+pub const DEFAULT_PLATE_ANGLES: [f32; 4] = [15.0, 75.0, 0.0, 45.0];
+
+// Approximate sRGB appearance of process inks on white paper (C, M, Y, K)
+const INK_COLORS: [[f32; 3]; 4] = [
+    [0.0, 174.0, 239.0],
+    [236.0, 0.0, 140.0],
+    [255.0, 241.0, 0.0],
+    [35.0, 31.0, 32.0],
+];
+
+// Convert one RGB color to ink coverages (0..1) for C, M, Y and K
+pub fn rgb_to_cmyk(r: u8, g: u8, b: u8, options: &SeparationOptions) -> [f32; 4] {
+    let c = 1.0 - r as f32 / 255.0;
+    let m = 1.0 - g as f32 / 255.0;
+    let y = 1.0 - b as f32 / 255.0;
+
+    // Black generation from the gray component, starting at black_start
+    let gray = c.min(m).min(y);
+    let start = options.black_start.clamp(0.0, 0.99);
+    let k = if gray > start {
+        options.black_generation.clamp(0.0, 1.0) * (gray - start) / (1.0 - start)
+    } else {
+        0.0
+    };
+
+    // Remove under-color; UCR restricts removal to neutral colors
+    let mut removal = options.under_color_removal.clamp(0.0, 1.0) * k;
+    if options.mode == SeparationMode::Ucr {
+        let max_cmy = c.max(m).max(y);
+        let neutrality = if max_cmy > 0.0 { gray / max_cmy } else { 1.0 };
+        removal *= neutrality;
+    }
+    let mut inks = [
+        (c - removal).max(0.0),
+        (m - removal).max(0.0),
+        (y - removal).max(0.0),
+        k,
+    ];
+
+    // Total area coverage limit: scale back C, M and Y, keep black
+    let total: f32 = inks.iter().sum();
+    if options.ink_limit > 0.0 && total > options.ink_limit {
+        let cmy = total - k;
+        let allowed = (options.ink_limit - k).max(0.0);
+        let scale = if cmy > 0.0 { allowed / cmy } else { 0.0 };
+        for ink in inks.iter_mut().take(3) {
+            *ink *= scale;
+        }
+    }
+
+    inks
+}
+
+// Separate an RGBA image and screen each plate; plates are 0 = ink, 255 = paper
+pub fn separate_and_screen(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    lpi: f32,
+    dpi: f32,
+    screens: &[PlateScreen; 4],
+    options: &SeparationOptions,
+) -> [Vec<u8>; 4] {
+    let size = width * height;
+
+    // Continuous-tone plates stored as "paper brightness" (255 = no ink)
+    let mut tones = [vec![0u8; size], vec![0u8; size], vec![0u8; size], vec![0u8; size]];
+    for i in 0..size {
+        let base = i * 4;
+        let inks = rgb_to_cmyk(rgba[base], rgba[base + 1], rgba[base + 2], options);
+        for (plate, &ink) in tones.iter_mut().zip(inks.iter()) {
+            plate[i] = ((1.0 - ink) * 255.0).round() as u8;
+        }
+    }
+
+    // Screen every plate with its own threshold array
+    let mut plates = [vec![0u8; size], vec![0u8; size], vec![0u8; size], vec![0u8; size]];
+    for (plate, (tone, screen)) in plates.iter_mut().zip(tones.iter().zip(screens.iter())) {
+        let thresholds = generate_threshold_array(lpi, screen.angle, dpi, screen.shape);
+        threshold_array_dither(tone, width, height, &thresholds, plate);
+    }
+
+    plates
+}
+
+// Simulate overprinting the plates on white paper, writing RGBA
+pub fn composite_preview(plates: &[Vec<u8>; 4], size: usize, output: &mut [u8]) {
+    for i in 0..size {
+        let mut color = [255.0f32; 3];
+        for (plate, ink) in plates.iter().zip(INK_COLORS.iter()) {
+            if plate[i] == 0 {
+                for channel in 0..3 {
+                    color[channel] *= ink[channel] / 255.0;
+                }
+            }
+        }
+        let base = i * 4;
+        output[base] = color[0].round() as u8;
+        output[base + 1] = color[1].round() as u8;
+        output[base + 2] = color[2].round() as u8;
+        output[base + 3] = 255;
+    }
+}
+
+// CMYK separation and per-plate halftoning of an RGBA image
+#[allow(clippy::too_many_arguments)]
+pub fn cmyk_halftone(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    dpi: f32,
+    angles: *const f32, // 4 plate angles (C, M, Y, K); null for the defaults
+    shapes: *const u32, // 4 DotShape values; null for round dots
+    options: SeparationOptions,
+    output: CmykOutput,
+    output_ptr: *mut u8,
+) {
+    if width == 0 || height == 0 { return; }
+    let size = width * height;
+    let rgba = unsafe { std::slice::from_raw_parts(rgba_ptr, size * 4) };
+
+    let angles_slice = if angles.is_null() {
+        &DEFAULT_PLATE_ANGLES[..]
+    } else {
+        unsafe { std::slice::from_raw_parts(angles, 4) }
+    };
+    let mut screens = [PlateScreen { angle: 0.0, shape: DotShape::Round }; 4];
+    for (plate, screen) in screens.iter_mut().enumerate() {
+        screen.angle = angles_slice[plate];
+        if !shapes.is_null() {
+            let shape_u32 = unsafe { *shapes.add(plate) };
+            // Out-of-range values fall back to round dots instead of transmuting garbage
+            if shape_u32 <= DotShape::Line as u32 {
+                screen.shape = unsafe { std::mem::transmute::<u32, DotShape>(shape_u32) };
+            }
+        }
+    }
+
+    let plates = separate_and_screen(rgba, width, height, lpi, dpi, &screens, &options);
+
+    match output {
+        CmykOutput::Plates => {
+            let out = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) };
+            for (plate_idx, plate) in plates.iter().enumerate() {
+                out[plate_idx * size..(plate_idx + 1) * size].copy_from_slice(plate);
+            }
+        }
+        CmykOutput::Preview => {
+            let out = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) };
+            composite_preview(&plates, size, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(mode: SeparationMode, black_generation: f32, black_start: f32) -> SeparationOptions {
+        SeparationOptions { mode, black_generation, black_start, under_color_removal: 1.0, ink_limit: 0.0 }
+    }
+
+    #[test]
+    fn neutral_grays_keep_their_ink_under_full_removal() {
+        // On a gray every ink is the same gray component, so each of C, M and Y
+        // plus the black that replaced it must add back up to that component
+        for mode in [SeparationMode::Ucr, SeparationMode::Gcr] {
+            for (generation, start) in [(1.0, 0.0), (0.6, 0.0), (1.0, 0.4), (0.3, 0.2)] {
+                for value in (0..=255u8).step_by(15) {
+                    let [c, m, y, k] = rgb_to_cmyk(value, value, value, &options(mode, generation, start));
+                    let gray = 1.0 - value as f32 / 255.0;
+                    for ink in [c, m, y] {
+                        assert!((ink + k - gray).abs() < 1e-5, "{:?} gray {}: {} + {}", mode, value, ink, k);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ucr_removes_under_color_only_from_neutrals() {
+        let brown = (150, 90, 60);
+        let gcr = rgb_to_cmyk(brown.0, brown.1, brown.2, &options(SeparationMode::Gcr, 1.0, 0.0));
+        let ucr = rgb_to_cmyk(brown.0, brown.1, brown.2, &options(SeparationMode::Ucr, 1.0, 0.0));
+        assert_eq!(gcr[3], ucr[3]);
+        for plate in 0..3 {
+            assert!(ucr[plate] > gcr[plate], "plate {}: UCR {} GCR {}", plate, ucr[plate], gcr[plate]);
+        }
+        // Pure hues have no gray component and get no black in either mode
+        for mode in [SeparationMode::Ucr, SeparationMode::Gcr] {
+            assert_eq!(rgb_to_cmyk(255, 0, 0, &options(mode, 1.0, 0.0)), [0.0, 1.0, 1.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn ink_limit_caps_total_coverage_and_keeps_black() {
+        let limited = SeparationOptions { ink_limit: 2.6, under_color_removal: 0.0, ..options(SeparationMode::Gcr, 1.0, 0.0) };
+        for (r, g, b) in [(0, 0, 0), (20, 10, 40), (60, 0, 0)] {
+            let unlimited = rgb_to_cmyk(r, g, b, &SeparationOptions { ink_limit: 0.0, ..limited });
+            let inks = rgb_to_cmyk(r, g, b, &limited);
+            assert!(inks.iter().sum::<f32>() <= 2.6 + 1e-5, "{:?}", inks);
+            assert_eq!(inks[3], unlimited[3]);
+        }
+    }
+
+    #[test]
+    fn plate_coverage_follows_the_ink_amount() {
+        let (width, height) = (96, 96);
+        let screens = DEFAULT_PLATE_ANGLES.map(|angle| PlateScreen { angle, shape: DotShape::Round });
+        let options = SeparationOptions { ink_limit: 0.0, ..options(SeparationMode::Gcr, 0.5, 0.0) };
+        let color = [200u8, 120, 80, 255];
+        let rgba: Vec<u8> = color.iter().copied().cycle().take(width * height * 4).collect();
+        let plates = separate_and_screen(&rgba, width, height, 60.0, 300.0, &screens, &options);
+        let inks = rgb_to_cmyk(color[0], color[1], color[2], &options);
+        for (plate, &ink) in plates.iter().zip(inks.iter()) {
+            let coverage = plate.iter().filter(|&&p| p == 0).count() as f32 / (width * height) as f32;
+            assert!((coverage - ink).abs() < 0.03, "coverage {} for ink {}", coverage, ink);
+        }
+    }
+}
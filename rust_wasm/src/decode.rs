@@ -0,0 +1,119 @@
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::fmt;
+use std::io::{BufRead, Cursor, Seek};
+use std::path::Path;
+
+// Image decoding for native builds
+//
+// Backend jobs load PNG, JPEG, GIF, BMP and Netpbm files here instead of
+// decoding around raw buffers. Files are decoded to 8-bit RGBA with the EXIF
+// orientation already applied, so a phone photo comes out upright. The
+// dither functions then take `grayscale()` (or `rgba` for the color ones)
+// exactly like the buffers the browser passes in. Animated GIFs can also be
+// read frame by frame, each composited onto the full canvas.
+
+// Decoded image, 4 bytes per pixel
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+// One frame of an animation; delay in hundredths of a second, None for still images
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub image: Image,
+    pub delay: Option<u16>,
+}
+
+// Why an input could not be decoded
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "could not read image: {}", err),
+            DecodeError::Image(err) => write!(f, "could not decode image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for DecodeError {
+    fn from(err: image::ImageError) -> Self {
+        DecodeError::Image(err)
+    }
+}
+
+impl Image {
+    // Luma plane, one byte per pixel, using the same weights as the editor
+    pub fn grayscale(&self) -> Vec<u8> {
+        self.rgba
+            .chunks_exact(4)
+            .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32).round() as u8)
+            .collect()
+    }
+}
+
+// Detect the format from the content, decode and apply the EXIF orientation
+fn decode_reader<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<Image, DecodeError> {
+    let mut decoder = reader.with_guessed_format()?.into_decoder()?;
+    // Missing or unreadable EXIF data just means the image is stored upright
+    let orientation = decoder.orientation().ok();
+    let mut decoded = DynamicImage::from_decoder(decoder)?;
+    if let Some(orientation) = orientation {
+        decoded.apply_orientation(orientation);
+    }
+
+    let rgba = decoded.into_rgba8();
+    Ok(Image {
+        width: rgba.width() as usize,
+        height: rgba.height() as usize,
+        rgba: rgba.into_raw(),
+    })
+}
+
+// Decode an image file
+pub fn load_image<P: AsRef<Path>>(path: P) -> Result<Image, DecodeError> {
+    decode_reader(ImageReader::open(path)?)
+}
+
+// Decode an image held in memory
+pub fn decode_image(bytes: &[u8]) -> Result<Image, DecodeError> {
+    decode_reader(ImageReader::new(Cursor::new(bytes)))
+}
+
+// Decode every frame of an animated GIF; other files give their single image
+pub fn load_frames<P: AsRef<Path>>(path: P) -> Result<Vec<Frame>, DecodeError> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    if reader.format() != Some(ImageFormat::Gif) {
+        return Ok(vec![Frame { image: decode_reader(reader)?, delay: None }]);
+    }
+
+    let frames = GifDecoder::new(reader.into_inner())?.into_frames().collect_frames()?;
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            let delay = (numerator as f64 / denominator.max(1) as f64 / 10.0).round().min(u16::MAX as f64) as u16;
+            let rgba = frame.into_buffer();
+            Frame {
+                image: Image { width: rgba.width() as usize, height: rgba.height() as usize, rgba: rgba.into_raw() },
+                delay: Some(delay),
+            }
+        })
+        .collect())
+}
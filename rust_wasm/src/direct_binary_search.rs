@@ -1,322 +1,548 @@
-use rand::Rng;
-use std::f32::consts::E;
+use crate::blue_noise::{blue_noise_mask, mask_threshold, DEFAULT_MASK_SIZE};
+use crate::hvs::HvsFilter;
+
+// Direct Binary Search (Analoui & Allebach, with the Lieberman & Allebach
+// efficient error evaluation)
+//
+// DBS minimizes the perceived error E = sum |p * (h - g)|^2, where g is the
+// target image, h the binary halftone and p the point spread function of the
+// eye (a low-pass filter). Because p is shift invariant, the effect of changing
+// pixel m by a only depends on the autocorrelation of the filter,
+// c_pp = p (x) p, and on the cross-correlation of the filter with the filtered
+// error, c_pe = c_pp * (h - g):
+//
+//   toggle m:    dE = a^2 c_pp(0) + 2 a c_pe(m)
+//   swap m, k:   dE = (a_m^2 + a_k^2) c_pp(0) + 2 a_m a_k c_pp(m - k)
+//                     + 2 a_m c_pe(m) + 2 a_k c_pe(k)
+//
+// So the search keeps one c_pe value per pixel (O(N) memory), evaluates each
+// trial change in O(1), and only touches the c_pp footprint when a change is
+// accepted. Tones are 0.0 (black) to 1.0 (white).
+//
+// Pixels are indices into a palette, so the same search handles multi-level
+// gray and color: a move changes a pixel to another entry, a is then the
+// per-channel difference between the entries, and dE is summed over channels
+// (each with its own c_pe plane and filter).
+
+// Chrominance filters are this many times wider than the luminance filter
+const CHROMA_SPREAD: f32 = 2.0;
+
+// Search settings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DbsOptions {
+    pub max_iterations: usize,    // Passes over the whole image
+    pub min_change_fraction: f32, // Stop once a pass changes fewer than this fraction of pixels
+    pub swap_radius: usize,       // Neighborhood searched for swaps; 0 tries toggles only
+    pub hvs: HvsFilter,           // Eye model, viewing distance and resolution
+}
 
-// --- Data Structures ---
+impl Default for DbsOptions {
+    fn default() -> Self {
+        DbsOptions {
+            max_iterations: 16,
+            min_change_fraction: 0.001,
+            swap_radius: 1,
+            hvs: HvsFilter::default(),
+        }
+    }
+}
 
-struct ErrorMetrics {
-    total_error: f64, // Use f64 for accumulated error precision
-    // filtered_target: Vec<f32>, // Target filtered once - can be computed directly in error calc?
-    // For simplicity matching TS, let's calculate error against unfiltered target but use HVS in error change calc.
-    // A more advanced version would filter the target once.
-    filtered_output: Vec<f32>,
+// Autocorrelation of a filter, indexed by offset within +-radius
+struct Autocorrelation {
+    radius: usize,
+    values: Vec<f32>,
 }
 
-// --- HVS Filter --- 
-
-fn create_hvs_filter(size: usize) -> (Vec<f32>, usize) {
-    if size == 0 || size % 2 == 0 { 
-        // Default to a minimal 3x3 if size is invalid
-        let radius = 1;
-        let size = 3;
-        let sigma = radius as f32 / 2.0; // Adjust sigma based on radius
-        let mut filter = vec![0.0; size * size];
-        let mut sum = 0.0;
-
-        for y in 0..size {
-            for x in 0..size {
-                let dx = x as isize - radius as isize;
-                let dy = y as isize - radius as isize;
-                let dist_sq = (dx * dx + dy * dy) as f32;
-                let weight = E.powf(-dist_sq / (2.0 * sigma * sigma));
-                filter[y * size + x] = weight;
-                sum += weight;
+impl Autocorrelation {
+    fn new(psf: &[f32], psf_radius: usize) -> Self {
+        let psf_size = 2 * psf_radius + 1;
+        let radius = 2 * psf_radius;
+        let size = 2 * radius + 1;
+        let mut values = vec![0.0; size * size];
+        for oy in 0..size {
+            for ox in 0..size {
+                let dx = ox as isize - radius as isize;
+                let dy = oy as isize - radius as isize;
+                let mut sum = 0.0;
+                for y in 0..psf_size as isize {
+                    let y2 = y + dy;
+                    if y2 < 0 || y2 >= psf_size as isize { continue; }
+                    for x in 0..psf_size as isize {
+                        let x2 = x + dx;
+                        if x2 < 0 || x2 >= psf_size as isize { continue; }
+                        sum += psf[(y * psf_size as isize + x) as usize] * psf[(y2 * psf_size as isize + x2) as usize];
+                    }
+                }
+                values[oy * size + ox] = sum;
             }
         }
-         // Normalize
-        if sum > 0.0 {
-            for val in filter.iter_mut() {
-                *val /= sum;
+        Autocorrelation { radius, values }
+    }
+
+    fn at(&self, dx: isize, dy: isize) -> f32 {
+        let r = self.radius as isize;
+        if dx.abs() > r || dy.abs() > r { return 0.0; }
+        self.values[((dy + r) * (2 * r + 1) + dx + r) as usize]
+    }
+}
+
+// Pixels as palette indices, with one running c_pe plane per channel
+struct DbsState<'a> {
+    width: usize,
+    height: usize,
+    channels: usize,
+    palette: &'a [f32], // Entry-major, `channels` values per entry
+    indices: Vec<usize>,
+    cpe: Vec<Vec<f32>>,
+    cpp: &'a [&'a Autocorrelation],
+}
+
+impl DbsState<'_> {
+    fn entry(&self, index: usize) -> &[f32] {
+        &self.palette[index * self.channels..(index + 1) * self.channels]
+    }
+
+    // Change pixel (x, y) to another palette entry, adding a_c * c_pp around it to every c_pe plane
+    fn set(&mut self, x: usize, y: usize, to: usize) {
+        let idx = y * self.width + x;
+        let from = self.indices[idx];
+        self.indices[idx] = to;
+        for c in 0..self.channels {
+            let a = self.palette[to * self.channels + c] - self.palette[from * self.channels + c];
+            if a == 0.0 { continue; }
+            let cpp = self.cpp[c];
+            let cpe = &mut self.cpe[c];
+            let r = cpp.radius as isize;
+            for dy in -r..=r {
+                let ny = y as isize + dy;
+                if ny < 0 || ny >= self.height as isize { continue; }
+                for dx in -r..=r {
+                    let nx = x as isize + dx;
+                    if nx < 0 || nx >= self.width as isize { continue; }
+                    cpe[ny as usize * self.width + nx as usize] += a * cpp.at(dx, dy);
+                }
             }
         }
-        return (filter, radius);
     }
 
-    let radius = size / 2;
-    let sigma = radius as f32 / 2.5; // As per TS code
-    let mut filter = vec![0.0; size * size];
-    let mut sum = 0.0;
-
-    for y in 0..size {
-        for x in 0..size {
-            let dx = x as isize - radius as isize;
-            let dy = y as isize - radius as isize;
-            let dist_sq = (dx * dx + dy * dy) as f32;
-            let weight = E.powf(-dist_sq / (2.0 * sigma * sigma));
-            filter[y * size + x] = weight;
-            sum += weight;
+    // Error change for pixel m taking entry `to` (while pixel k takes m's entry when swapping)
+    fn change(&self, m: usize, to: usize, swap: Option<(usize, isize, isize)>) -> f32 {
+        let (from, target) = (self.entry(self.indices[m]), self.entry(to));
+        let mut change = 0.0;
+        for c in 0..self.channels {
+            let a = target[c] - from[c];
+            if a == 0.0 { continue; }
+            let cpp0 = self.cpp[c].at(0, 0);
+            change += match swap {
+                None => a * a * cpp0 + 2.0 * a * self.cpe[c][m],
+                Some((k, dx, dy)) => {
+                    2.0 * a * a * (cpp0 - self.cpp[c].at(dx, dy)) + 2.0 * a * (self.cpe[c][m] - self.cpe[c][k])
+                }
+            };
         }
+        change
     }
+}
 
-    // Normalize
-    if sum > 0.0 {
-        for val in filter.iter_mut() {
-            *val /= sum;
+// c_pp * (h - g) by direct convolution
+fn initial_cpe(halftone: &[f32], target: &[f32], width: usize, height: usize, cpp: &Autocorrelation) -> Vec<f32> {
+    let r = cpp.radius as isize;
+    let mut cpe = vec![0.0; width * height];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let mut sum = 0.0;
+            for dy in -r..=r {
+                let ny = y + dy;
+                if ny < 0 || ny >= height as isize { continue; }
+                for dx in -r..=r {
+                    let nx = x + dx;
+                    if nx < 0 || nx >= width as isize { continue; }
+                    let n = ny as usize * width + nx as usize;
+                    sum += cpp.at(dx, dy) * (halftone[n] - target[n]);
+                }
+            }
+            cpe[y as usize * width + x as usize] = sum;
         }
     }
-
-    (filter, radius)
+    cpe
 }
 
-// --- Precomputation (Memory Intensive!) ---
-
-// Represents the effect of the HVS filter centered at a specific pixel.
-// NOTE: This function allocates a potentially huge amount of memory:
-// num_pixels * num_pixels * sizeof(f32). 
-// E.g., 512x512 image -> ~275 GB!
-// This is likely infeasible for typical WASM use cases without significant optimization
-// or a different DBS approach that avoids storing all basis functions explicitly.
-fn precompute_filtered_basis_functions(
-    width: usize, 
-    height: usize, 
-    filter_size: usize, 
-    filter: &[f32],
-    radius: usize
-) -> Vec<Vec<f32>> { // Outer Vec: pixel index, Inner Vec: effect on all pixels
-    let num_pixels = width * height;
-    // Initialize with sparse representation potentially?
-    // For now, stick to the dense version as implied by TS, but comment heavily.
-    let mut all_filtered_basis = vec![vec![0.0f32; num_pixels]; num_pixels]; 
-
-    for y in 0..height {
-        for x in 0..width {
-            let center_idx = y * width + x;
-            let mut current_basis = vec![0.0f32; num_pixels]; // Effect of toggling pixel (x, y)
-
-            for fy_offset in 0..filter_size {
-                for fx_offset in 0..filter_size {
-                    let filter_idx = fy_offset * filter_size + fx_offset;
-                    let filter_weight = filter[filter_idx];
-                    if filter_weight == 0.0 { continue; }
-
-                    // Calculate the image coordinates (nx, ny) affected by this filter element
-                    let nx = x as isize + fx_offset as isize - radius as isize;
-                    let ny = y as isize + fy_offset as isize - radius as isize;
-
-                    // Check bounds
-                    if nx >= 0 && nx < width as isize && ny >= 0 && ny < height as isize {
-                        let affected_idx = (ny as usize) * width + (nx as usize);
-                        // The value stored is the filter weight at that relative position
-                        current_basis[affected_idx] = filter_weight;
+// Search over palette indices. `palette` holds `channels` values per entry,
+// `targets` one plane per channel in the same space, `cpp` one filter per channel.
+fn search(
+    width: usize,
+    height: usize,
+    palette: &[f32],
+    targets: &[Vec<f32>],
+    cpp: &[&Autocorrelation],
+    initial: Vec<usize>,
+    options: &DbsOptions,
+) -> Vec<usize> {
+    let size = width * height;
+    let channels = targets.len();
+    let colors = palette.len() / channels;
+    let cpe = (0..channels)
+        .map(|c| {
+            let plane: Vec<f32> = initial.iter().map(|&i| palette[i * channels + c]).collect();
+            initial_cpe(&plane, &targets[c], width, height, cpp[c])
+        })
+        .collect();
+    let mut state = DbsState { width, height, channels, palette, indices: initial, cpe, cpp };
+
+    let swap_radius = options.swap_radius as isize;
+    for _ in 0..options.max_iterations {
+        let mut changes = 0;
+        for y in 0..height {
+            for x in 0..width {
+                let m = y * width + x;
+                let current = state.indices[m];
+
+                // Every other palette entry, then every swap with a different pixel nearby; keep the best
+                let mut best_change = 0.0;
+                let mut best_move = None;
+                for to in (0..colors).filter(|&to| to != current) {
+                    let change = state.change(m, to, None);
+                    if change < best_change {
+                        best_change = change;
+                        best_move = Some((to, None));
+                    }
+                }
+                for dy in -swap_radius..=swap_radius {
+                    let ny = y as isize + dy;
+                    if ny < 0 || ny >= height as isize { continue; }
+                    for dx in -swap_radius..=swap_radius {
+                        let nx = x as isize + dx;
+                        if nx < 0 || nx >= width as isize || (dx == 0 && dy == 0) { continue; }
+                        let k = ny as usize * width + nx as usize;
+                        let other = state.indices[k];
+                        if other == current { continue; }
+                        let change = state.change(m, other, Some((k, dx, dy)));
+                        if change < best_change {
+                            best_change = change;
+                            best_move = Some((other, Some((nx as usize, ny as usize))));
+                        }
                     }
                 }
+
+                // Accept only real improvements, so float noise cannot make two pixels trade forever
+                let Some((to, swap)) = best_move else { continue };
+                if best_change >= -1e-6 { continue; }
+                state.set(x, y, to);
+                if let Some((kx, ky)) = swap {
+                    state.set(kx, ky, current);
+                }
+                changes += 1;
             }
-            all_filtered_basis[center_idx] = current_basis;
         }
+        if (changes as f32) < options.min_change_fraction * size as f32 { break; }
     }
+    state.indices
+}
 
-    all_filtered_basis
+// Blue-noise mask value for a pixel, in (0, 1)
+fn mask_at(mask: &[f32], x: usize, y: usize) -> f32 {
+    mask[(y % DEFAULT_MASK_SIZE) * DEFAULT_MASK_SIZE + x % DEFAULT_MASK_SIZE]
 }
 
+// DBS halftone of a grayscale plane; one byte per pixel (0 or 255).
+// The search starts from a blue-noise dither at the given threshold.
+pub fn direct_binary_search(input: &[u8], width: usize, height: usize, threshold: u8, options: &DbsOptions) -> Vec<u8> {
+    let size = width * height;
+    if size == 0 { return Vec::new(); }
+
+    let target: Vec<f32> = input.iter().map(|&p| p as f32 / 255.0).collect();
+    let mask = blue_noise_mask(DEFAULT_MASK_SIZE, 0);
+    let initial = (0..size)
+        .map(|i| {
+            let limit = mask_threshold(mask_at(&mask, i % width, i / width), threshold);
+            if (input[i] as f32) < limit { 0 } else { 1 }
+        })
+        .collect();
+
+    let (psf, psf_radius) = options.hvs.kernel();
+    let cpp = Autocorrelation::new(&psf, psf_radius);
+    let indices = search(width, height, &[0.0, 1.0], &[target], &[&cpp], initial, options);
+    indices.iter().map(|&i| if i == 1 { 255 } else { 0 }).collect()
+}
+
+// DBS with `levels` evenly spaced gray tones (2 - 256); one byte per pixel.
+// The search starts from a blue-noise multi-level dither.
+pub fn direct_binary_search_levels(input: &[u8], width: usize, height: usize, levels: usize, options: &DbsOptions) -> Vec<u8> {
+    let size = width * height;
+    if size == 0 { return Vec::new(); }
+    let levels = levels.clamp(2, 256);
+    let steps = (levels - 1) as f32;
+
+    let palette: Vec<f32> = (0..levels).map(|l| l as f32 / steps).collect();
+    let target: Vec<f32> = input.iter().map(|&p| p as f32 / 255.0).collect();
+    let mask = blue_noise_mask(DEFAULT_MASK_SIZE, 0);
+    let initial = (0..size)
+        .map(|i| {
+            let scaled = target[i] * steps;
+            let base = scaled.floor();
+            let up = (scaled - base) > mask_at(&mask, i % width, i / width);
+            (base as usize + up as usize).min(levels - 1)
+        })
+        .collect();
+
+    let (psf, psf_radius) = options.hvs.kernel();
+    let cpp = Autocorrelation::new(&psf, psf_radius);
+    let indices = search(width, height, &palette, &[target], &[&cpp], initial, options);
+    indices.iter().map(|&i| (palette[i] * 255.0).round() as u8).collect()
+}
 
-// --- Error Calculation & Updates ---
+// sRGB to linearized CIELAB (YyCxCz, Flohr et al.): a linear transform of XYZ,
+// so filtering the error stays exact, with Lab-like scaling for comparing colors
+fn srgb_to_yycxcz(color: [u8; 3]) -> [f32; 3] {
+    let linear = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(color[0]), linear(color[1]), linear(color[2]));
+    // XYZ relative to the D65 white point
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.0890;
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
 
-// Calculates the initial error metrics based on the initial pattern.
-// This involves effectively filtering the initial binary pattern.
-fn calculate_initial_error_metrics(
-    target_image: &[f32],
-    binary_pattern: &[u8],
+// DBS over a color palette; returns one palette index per pixel.
+// Error is measured in YyCxCz, with chrominance seen through a wider filter
+// than luminance because the eye resolves color more coarsely.
+pub fn direct_binary_search_palette(
+    rgba: &[u8],
     width: usize,
     height: usize,
-    all_filtered_basis: &[Vec<f32>], // Borrow precomputed basis functions
-) -> ErrorMetrics {
-    let num_pixels = width * height;
-    let mut filtered_output = vec![0.0f32; num_pixels];
-    let mut total_error: f64 = 0.0;
-
-    // Build the initial filtered output based on the binary pattern
-    for i in 0..num_pixels {
-        if binary_pattern[i] == 1 {
-            let basis = &all_filtered_basis[i];
-            for j in 0..num_pixels {
-                 // This loop is the core of applying the precomputed filter effects
-                filtered_output[j] += basis[j] * 255.0; 
-            }
-        }
-    }
+    palette: &[[u8; 3]],
+    options: &DbsOptions,
+) -> Vec<u8> {
+    let size = width * height;
+    if size == 0 || palette.is_empty() { return vec![0; size]; }
+    let palette = &palette[..palette.len().min(256)];
 
-    // Calculate initial squared error against the *original* target 
-    // (Simpler than filtering target, error change calc handles HVS weighting)
-    for i in 0..num_pixels {
-        let error = target_image[i] - filtered_output[i];
-        total_error += (error * error) as f64;
+    let entries: Vec<f32> = palette.iter().flat_map(|&c| srgb_to_yycxcz(c)).collect();
+    let mut targets = vec![vec![0.0f32; size]; 3];
+    for i in 0..size {
+        let color = srgb_to_yycxcz([rgba[i * 4], rgba[i * 4 + 1], rgba[i * 4 + 2]]);
+        for c in 0..3 {
+            targets[c][i] = color[c];
+        }
     }
 
-    ErrorMetrics {
-        total_error,
-        filtered_output,
+    // Start from Floyd-Steinberg error diffusion in the same space
+    let mut initial = vec![0usize; size];
+    let mut carried = targets.clone();
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let distance = |e: usize| (0..3).map(|c| (entries[e * 3 + c] - carried[c][i]).powi(2)).sum::<f32>();
+            let nearest = (0..palette.len()).min_by(|&a, &b| distance(a).total_cmp(&distance(b))).unwrap_or(0);
+            initial[i] = nearest;
+            for (c, plane) in carried.iter_mut().enumerate() {
+                let error = plane[i] - entries[nearest * 3 + c];
+                if x + 1 < width { plane[i + 1] += error * 7.0 / 16.0; }
+                if y + 1 < height {
+                    if x > 0 { plane[i + width - 1] += error * 3.0 / 16.0; }
+                    plane[i + width] += error * 5.0 / 16.0;
+                    if x + 1 < width { plane[i + width + 1] += error * 1.0 / 16.0; }
+                }
+            }
+        }
     }
-}
 
-// Efficiently calculate the change in total squared error if pixel (x, y) is toggled.
-fn calculate_toggle_error_change(
-    target_image: &[f32],
-    binary_pattern: &[u8],
-    x: usize, y: usize,
-    width: usize, 
-    _height: usize, // Height unused but kept for consistency
-    all_filtered_basis: &[Vec<f32>],
-    current_metrics: &ErrorMetrics,
-) -> f64 {
-    let idx = y * width + x;
-    let basis = &all_filtered_basis[idx]; // Get the precomputed effect of toggling this pixel
-    let num_pixels = target_image.len();
-
-    let current_value = binary_pattern[idx];
-    let value_diff = if current_value == 0 { 255.0 } else { -255.0 }; // Change in pixel value (0->255 or 1->0)
-
-    let mut error_change: f64 = 0.0;
-
-    // Formula derived from difference of squares: NewError^2 - OldError^2
-    // NewError = Target - (FilteredOutput + Basis * ValueDiff)
-    // OldError = Target - FilteredOutput
-    // Change = sum( (OldError - Basis*ValueDiff)^2 - OldError^2 )
-    // Change = sum( OldError^2 - 2*OldError*Basis*ValueDiff + (Basis*ValueDiff)^2 - OldError^2 )
-    // Change = sum( -2 * OldError * Basis * ValueDiff + (Basis * ValueDiff)^2 )
-
-    for i in 0..num_pixels {
-        if basis[i] == 0.0 { continue; } // Optimization: Skip pixels unaffected by the toggle
-
-        let old_error_pixel = target_image[i] - current_metrics.filtered_output[i];
-        let basis_effect = basis[i] * value_diff;
-        
-        let term1 = -2.0 * old_error_pixel * basis_effect;
-        let term2 = basis_effect * basis_effect;
-
-        error_change += (term1 + term2) as f64;
-    }
+    let (psf, psf_radius) = options.hvs.kernel();
+    let luminance = Autocorrelation::new(&psf, psf_radius);
+    let chroma_hvs = HvsFilter { viewing_distance: options.hvs.viewing_distance * CHROMA_SPREAD, ..options.hvs };
+    let (chroma_psf, chroma_radius) = chroma_hvs.kernel();
+    let chrominance = Autocorrelation::new(&chroma_psf, chroma_radius);
 
-    error_change
+    let indices = search(width, height, &entries, &targets, &[&luminance, &chrominance, &chrominance], initial, options);
+    indices.iter().map(|&i| i as u8).collect()
 }
 
-// Update the error metrics after a successful toggle
-fn update_error_metrics_after_toggle(
-    metrics: &mut ErrorMetrics,
-    error_change: f64,
-    toggled_idx: usize,
-    value_diff: f32, // +255.0 if 0->1, -255.0 if 1->0
-    all_filtered_basis: &[Vec<f32>],
+pub fn direct_binary_search_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    threshold: u8,
+    output_ptr: *mut u8,
 ) {
-    metrics.total_error += error_change;
-    
-    // Update the filtered output image - crucial for next error change calculation
-    let basis = &all_filtered_basis[toggled_idx];
-    for i in 0..metrics.filtered_output.len() {
-        if basis[i] != 0.0 {
-             metrics.filtered_output[i] += basis[i] * value_diff;
-        }
+    if width == 0 || height == 0 { return; }
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) }; // RGBA output
+
+    let plane = direct_binary_search(input, width, height, threshold, &DbsOptions::default());
+
+    // Write RGBA output pixels
+    for (i, &value) in plane.iter().enumerate() {
+        output[i * 4] = value;
+        output[i * 4 + 1] = value;
+        output[i * 4 + 2] = value;
+        output[i * 4 + 3] = 255; // Alpha
     }
 }
 
-// --- Main DBS Function ---
-
-pub fn direct_binary_search_dither(
+// DBS with explicit search settings; one byte per pixel output
+pub fn dbs_dither(
     input_ptr: *const u8,
     width: usize,
     height: usize,
     threshold: u8,
+    options: &DbsOptions,
     output_ptr: *mut u8,
 ) {
     if width == 0 || height == 0 { return; }
     let size = width * height;
     let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
-    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) }; // RGBA output
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+    output.copy_from_slice(&direct_binary_search(input, width, height, threshold, options));
+}
+
+// Multi-level gray DBS with explicit search settings; one byte per pixel output
+pub fn dbs_levels_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    levels: usize,
+    options: &DbsOptions,
+    output_ptr: *mut u8,
+) {
+    if width == 0 || height == 0 { return; }
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+    output.copy_from_slice(&direct_binary_search_levels(input, width, height, levels, options));
+}
 
-    // 1. Initial Binary Pattern (Thresholding)
-    let mut binary_pattern: Vec<u8> = input.iter().map(|&p| if p < threshold { 0 } else { 1 }).collect();
-    let target_image: Vec<f32> = input.iter().map(|&p| p as f32).collect();
-
-    // 2. HVS Filter
-    let filter_size = 7; // Match TS
-    let (hvs_filter, radius) = create_hvs_filter(filter_size);
-
-    // 3. Precompute Filtered Basis Functions (Potentially HUGE memory allocation)
-    // Add checks or alternative strategies for large images in real applications.
-    println!("DBS: Starting basis function precomputation (may use significant memory)... Width: {}, Height: {}", width, height);
-    let all_filtered_basis = precompute_filtered_basis_functions(width, height, filter_size, &hvs_filter, radius);
-    println!("DBS: Basis function precomputation complete.");
-
-    // 4. Initial Error Calculation
-    println!("DBS: Calculating initial error metrics...");
-    let mut current_metrics = calculate_initial_error_metrics(&target_image, &binary_pattern, width, height, &all_filtered_basis);
-    println!("DBS: Initial Total Error: {}", current_metrics.total_error);
-
-    // 5. DBS Iterative Optimization
-    let max_iterations = 10; // Fewer iterations than TS for potentially faster WASM execution
-    let min_improvement_threshold = 0.001; // Stop if improvement is negligible
-    let swap_candidates_per_iter = (width * height).min(5000); // Limit candidates
-    let mut rng = rand::thread_rng();
-
-    println!("DBS: Starting optimization iterations...");
-    for iter in 0..max_iterations {
-        let mut changes_in_iteration = 0;
-        let error_before_iter = current_metrics.total_error;
-
-        for _ in 0..swap_candidates_per_iter {
-            // Pick random pixel to potentially toggle
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            let idx = y * width + x;
-
-            // Calculate the potential error change
-            let error_change = calculate_toggle_error_change(
-                &target_image, &binary_pattern, 
-                x, y, width, height, 
-                &all_filtered_basis, &current_metrics
-            );
-
-            // If toggling reduces error, accept the change
-            if error_change < 0.0 {
-                let current_value = binary_pattern[idx];
-                let value_diff = if current_value == 0 { 255.0 } else { -255.0 };
-                
-                // Update binary pattern
-                binary_pattern[idx] = 1 - current_value; // Toggle 0 <-> 1
-                
-                // Update error metrics
-                update_error_metrics_after_toggle(&mut current_metrics, error_change, idx, value_diff, &all_filtered_basis);
-                changes_in_iteration += 1;
+// Palette DBS on RGBA input (palette_ptr holds `colors` RGB triplets); RGBA output in palette colors
+pub fn dbs_palette_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    palette_ptr: *const u8,
+    colors: usize,
+    options: &DbsOptions,
+    output_ptr: *mut u8,
+) {
+    if width == 0 || height == 0 || colors == 0 { return; }
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size * 4) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) };
+    let palette: Vec<[u8; 3]> = unsafe { std::slice::from_raw_parts(palette_ptr, colors.min(256) * 3) }
+        .chunks_exact(3)
+        .map(|c| [c[0], c[1], c[2]])
+        .collect();
+
+    let indices = direct_binary_search_palette(input, width, height, &palette, options);
+    for (i, &index) in indices.iter().enumerate() {
+        let color = palette[index as usize];
+        output[i * 4..i * 4 + 3].copy_from_slice(&color);
+        output[i * 4 + 3] = 255; // Alpha
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hvs::HvsModel;
+
+    // Perceived error sum |p * (h - g)|^2 by direct full convolution, the quantity DBS minimizes
+    fn perceived_error(halftone: &[f32], target: &[f32], width: usize, height: usize, hvs: &HvsFilter) -> f64 {
+        let (psf, r) = hvs.kernel();
+        let diameter = 2 * r + 1;
+        let mut total = 0.0f64;
+        for oy in 0..height + 2 * r {
+            for ox in 0..width + 2 * r {
+                let mut filtered = 0.0f64;
+                for ky in 0..diameter {
+                    for kx in 0..diameter {
+                        let (x, y) = ((ox + kx) as isize - 2 * r as isize, (oy + ky) as isize - 2 * r as isize);
+                        if x < 0 || y < 0 || x >= width as isize || y >= height as isize { continue; }
+                        let i = y as usize * width + x as usize;
+                        filtered += psf[ky * diameter + kx] as f64 * (halftone[i] - target[i]) as f64;
+                    }
+                }
+                total += filtered * filtered;
             }
         }
+        total
+    }
+
+    // Gradient with a soft disk, so the search has edges and flat areas to work on
+    fn test_image(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let disk = if (x - 12.0).powi(2) + (y - 10.0).powi(2) < 36.0 { 90.0 } else { 0.0 };
+                (x * 255.0 / width as f32 * 0.7 + disk).min(255.0) as u8
+            })
+            .collect()
+    }
 
-        let error_after_iter = current_metrics.total_error;
-        println!("DBS: Iteration {}, Changes: {}, Error: {}", iter + 1, changes_in_iteration, error_after_iter);
+    fn to_tones(pixels: &[u8]) -> Vec<f32> {
+        pixels.iter().map(|&p| p as f32 / 255.0).collect()
+    }
 
-        // Check for convergence
-        if changes_in_iteration == 0 {
-            println!("DBS: Converged (no changes).");
-            break;
-        }
-        let improvement = error_before_iter - error_after_iter;
-        if improvement < 0.0 { // Should not happen ideally
-             println!("DBS: Warning - error increased?");
-        }
-        if error_before_iter > 0.0 && (improvement / error_before_iter) < min_improvement_threshold {
-            println!("DBS: Converged (minimal improvement).");
-            break;
+    #[test]
+    fn search_never_increases_the_perceived_error() {
+        let (width, height) = (28, 22);
+        let input = test_image(width, height);
+        let target = to_tones(&input);
+        for model in [HvsModel::Gaussian, HvsModel::Nasanen, HvsModel::MannosSakrison] {
+            for swap_radius in [0, 1] {
+                let hvs = HvsFilter { model, ..HvsFilter::default() };
+                let mut previous = f64::INFINITY;
+                for max_iterations in [0, 1, 2, 4, 8] {
+                    let options = DbsOptions { max_iterations, min_change_fraction: 0.0, swap_radius, hvs };
+                    let halftone = direct_binary_search(&input, width, height, 128, &options);
+                    let error = perceived_error(&to_tones(&halftone), &target, width, height, &hvs);
+                    assert!(error <= previous + 1e-6, "{:?} swaps {}: {} passes {} > {}", model, swap_radius, max_iterations, error, previous);
+                    previous = error;
+                }
+            }
         }
-        if iter == max_iterations - 1 {
-             println!("DBS: Reached max iterations.");
+    }
+
+    #[test]
+    fn multi_level_search_uses_only_palette_levels_and_lowers_error() {
+        let (width, height) = (24, 20);
+        let input = test_image(width, height);
+        let target = to_tones(&input);
+        let hvs = HvsFilter::default();
+        let levels = 4;
+        let start = direct_binary_search_levels(&input, width, height, levels, &DbsOptions { max_iterations: 0, ..DbsOptions::default() });
+        let result = direct_binary_search_levels(&input, width, height, levels, &DbsOptions::default());
+        assert!(result.iter().all(|&p| [0, 85, 170, 255].contains(&p)));
+        let before = perceived_error(&to_tones(&start), &target, width, height, &hvs);
+        let after = perceived_error(&to_tones(&result), &target, width, height, &hvs);
+        assert!(after < before, "{} >= {}", after, before);
+    }
+
+    #[test]
+    fn flat_tones_keep_their_coverage() {
+        let (width, height) = (40, 40);
+        for value in [32u8, 64, 128, 192] {
+            let output = direct_binary_search(&vec![value; width * height], width, height, 128, &DbsOptions::default());
+            let coverage = output.iter().filter(|&&p| p == 255).count() as f32 / (width * height) as f32;
+            assert!((coverage - value as f32 / 255.0).abs() < 0.03, "gray {}: coverage {}", value, coverage);
         }
     }
 
-    // 6. Final Output Generation
-    for i in 0..size {
-        let value = if binary_pattern[i] == 1 { 255 } else { 0 };
-        let base_idx_out = i * 4;
-        output[base_idx_out] = value;
-        output[base_idx_out + 1] = value;
-        output[base_idx_out + 2] = value;
-        output[base_idx_out + 3] = 255; // Alpha
+    #[test]
+    fn palette_search_keeps_colors_already_in_the_palette() {
+        let (width, height) = (12, 10);
+        let palette = [[0, 0, 0], [200, 30, 40], [255, 255, 255], [20, 90, 200]];
+        // Left half one palette color, right half another
+        let rgba: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let [r, g, b] = if i % width < width / 2 { palette[1] } else { palette[3] };
+                [r, g, b, 255]
+            })
+            .collect();
+        let indices = direct_binary_search_palette(&rgba, width, height, &palette, &DbsOptions::default());
+        for (i, &index) in indices.iter().enumerate() {
+            assert_eq!(index, if i % width < width / 2 { 1 } else { 3 }, "pixel {}", i);
+        }
     }
-     println!("DBS: Dithering complete.");
-} 
\ No newline at end of file
+}
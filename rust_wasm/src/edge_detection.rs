@@ -1,3 +1,227 @@
+use wasm_bindgen::prelude::*;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+// Edge detection
+//
+// Sobel, Scharr and Prewitt are 3x3 gradient operators that differ only in how
+// they smooth across the derivative: [1 2 1], [3 10 3] and [1 1 1]. Their
+// responses are divided by that weight, so a sharp 0 to 255 step has magnitude
+// 255 whichever operator measured it and one threshold means the same thing for
+// all of them. Laplacian of Gaussian marks zero crossings of the Laplacian of
+// the smoothed image; Canny thins the smoothed Sobel gradient to single-pixel
+// ridges (non-maximum suppression) and keeps weak ridges only where they
+// connect to strong ones (hysteresis). Pixels outside the image repeat the
+// nearest edge pixel, so borders get real responses instead of a black frame.
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeDetector {
+    Sobel = 0,
+    Scharr = 1,
+    Prewitt = 2,
+    LaplacianOfGaussian = 3,
+    Canny = 4,
+}
+
+// Settings for find_edges; each detector ignores the ones it does not use
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeOptions {
+    pub detector: EdgeDetector,
+    pub threshold: f32,     // Gradient magnitude for an edge (Canny: the high threshold)
+    pub low_threshold: f32, // Canny only: weaker ridges kept when connected to strong ones
+    pub sigma: f32,         // Gaussian smoothing before differentiation; 0 for none
+}
+
+impl Default for EdgeOptions {
+    fn default() -> Self {
+        EdgeOptions { detector: EdgeDetector::Canny, threshold: 64.0, low_threshold: 32.0, sigma: 1.4 }
+    }
+}
+
+// Per-pixel gradient: magnitude in gray levels, orientation in radians from +x
+// towards +y (down), in (-pi, pi]
+#[derive(Clone, Debug, Default)]
+pub struct Gradient {
+    pub magnitude: Vec<f32>,
+    pub orientation: Vec<f32>,
+}
+
+// Cross-derivative smoothing weights of each 3x3 operator
+fn smoothing(detector: EdgeDetector) -> [f32; 3] {
+    match detector {
+        EdgeDetector::Scharr => [3.0, 10.0, 3.0],
+        EdgeDetector::Prewitt => [1.0, 1.0, 1.0],
+        // LoG and Canny measure their gradient with Sobel on the smoothed image
+        EdgeDetector::Sobel | EdgeDetector::LaplacianOfGaussian | EdgeDetector::Canny => [1.0, 2.0, 1.0],
+    }
+}
+
+// Pixel at (x, y) with coordinates clamped to the image
+fn clamped(plane: &[f32], width: usize, height: usize, x: isize, y: isize) -> f32 {
+    let x = x.clamp(0, width as isize - 1) as usize;
+    let y = y.clamp(0, height as isize - 1) as usize;
+    plane[y * width + x]
+}
+
+// Separable Gaussian blur with clamped borders; sigma <= 0 returns the plane unchanged
+pub fn gaussian_blur(plane: &[f32], width: usize, height: usize, sigma: f32) -> Vec<f32> {
+    if sigma <= 0.0 || width == 0 || height == 0 { return plane.to_vec(); }
+    let radius = (3.0 * sigma).ceil() as isize;
+    let mut weights: Vec<f32> = (-radius..=radius).map(|d| (-(d * d) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+    let sum: f32 = weights.iter().sum();
+    weights.iter_mut().for_each(|w| *w /= sum);
+
+    let mut horizontal = vec![0.0; plane.len()];
+    for y in 0..height {
+        for x in 0..width {
+            horizontal[y * width + x] = (-radius..=radius)
+                .map(|d| weights[(d + radius) as usize] * clamped(plane, width, height, x as isize + d, y as isize))
+                .sum();
+        }
+    }
+    let mut blurred = vec![0.0; plane.len()];
+    for y in 0..height {
+        for x in 0..width {
+            blurred[y * width + x] = (-radius..=radius)
+                .map(|d| weights[(d + radius) as usize] * clamped(&horizontal, width, height, x as isize, y as isize + d))
+                .sum();
+        }
+    }
+    blurred
+}
+
+// Gradient of an already smoothed plane with one of the 3x3 operators
+pub fn plane_gradient(plane: &[f32], width: usize, height: usize, detector: EdgeDetector) -> Gradient {
+    let weights = smoothing(detector);
+    let scale = 1.0 / weights.iter().sum::<f32>();
+    let mut gradient = Gradient { magnitude: vec![0.0; plane.len()], orientation: vec![0.0; plane.len()] };
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let mut gx = 0.0;
+            let mut gy = 0.0;
+            for (k, &w) in weights.iter().enumerate() {
+                let d = k as isize - 1;
+                gx += w * (clamped(plane, width, height, xi + 1, yi + d) - clamped(plane, width, height, xi - 1, yi + d));
+                gy += w * (clamped(plane, width, height, xi + d, yi + 1) - clamped(plane, width, height, xi + d, yi - 1));
+            }
+            let (gx, gy) = (gx * scale, gy * scale);
+            let i = y * width + x;
+            gradient.magnitude[i] = (gx * gx + gy * gy).sqrt();
+            gradient.orientation[i] = gy.atan2(gx);
+        }
+    }
+    gradient
+}
+
+// Gradient magnitude and orientation of a grayscale plane, smoothed by sigma first
+pub fn gradient(input: &[u8], width: usize, height: usize, detector: EdgeDetector, sigma: f32) -> Gradient {
+    let plane: Vec<f32> = input.iter().map(|&p| p as f32).collect();
+    plane_gradient(&gaussian_blur(&plane, width, height, sigma), width, height, detector)
+}
+
+// Canny: keep gradient ridges across their orientation, then trace from strong to weak pixels
+fn canny(gradient: &Gradient, width: usize, height: usize, low: f32, high: f32) -> Vec<u8> {
+    let magnitude = &gradient.magnitude;
+    let mut ridge = vec![false; magnitude.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let m = magnitude[i];
+            if m < low || m == 0.0 { continue; }
+            // Orientation rounded to one of four neighbour directions
+            let sector = ((gradient.orientation[i] / (PI / 4.0)).round() as isize).rem_euclid(4);
+            let (dx, dy) = [(1, 0), (1, 1), (0, 1), (-1, 1)][sector as usize];
+            // Outside the image counts as no gradient, so ridges along the border survive
+            let neighbour = |nx: isize, ny: isize| {
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { 0.0 } else { magnitude[ny as usize * width + nx as usize] }
+            };
+            let (xi, yi) = (x as isize, y as isize);
+            let ahead = neighbour(xi + dx, yi + dy);
+            let behind = neighbour(xi - dx, yi - dy);
+            // Ties go to the pixel further along, so a two-pixel step keeps one
+            ridge[i] = m > ahead && m >= behind;
+        }
+    }
+
+    let mut output = vec![0u8; magnitude.len()];
+    let mut queue: VecDeque<usize> = (0..magnitude.len()).filter(|&i| ridge[i] && magnitude[i] >= high).collect();
+    for &i in &queue {
+        output[i] = 255;
+    }
+    while let Some(i) = queue.pop_front() {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { continue; }
+                let n = ny as usize * width + nx as usize;
+                if ridge[n] && output[n] == 0 {
+                    output[n] = 255;
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+    output
+}
+
+// Zero crossings of the Laplacian where the gradient is above the threshold,
+// marked on the side closer to zero (the negative side on a tie) so lines stay
+// one pixel wide
+fn laplacian_zero_crossings(smoothed: &[f32], gradient: &Gradient, width: usize, height: usize, threshold: f32) -> Vec<u8> {
+    let mut laplacian = vec![0.0; smoothed.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            laplacian[y * width + x] = clamped(smoothed, width, height, xi - 1, yi)
+                + clamped(smoothed, width, height, xi + 1, yi)
+                + clamped(smoothed, width, height, xi, yi - 1)
+                + clamped(smoothed, width, height, xi, yi + 1)
+                - 4.0 * smoothed[y * width + x];
+        }
+    }
+
+    let mut output = vec![0u8; smoothed.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            if gradient.magnitude[i] <= threshold { continue; }
+            let l = laplacian[i];
+            let crossing = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|&(dx, dy)| {
+                let n = clamped(&laplacian, width, height, x as isize + dx, y as isize + dy);
+                let closer = l.abs() < n.abs() || l.abs() == n.abs() && l < 0.0;
+                (l < 0.0 && n >= 0.0 || l > 0.0 && n <= 0.0) && closer || l == 0.0 && n != 0.0
+            });
+            if crossing {
+                output[i] = 255;
+            }
+        }
+    }
+    output
+}
+
+// Binary edge map (0 or 255 per pixel) and the gradient it was measured from
+pub fn find_edges(input: &[u8], width: usize, height: usize, options: &EdgeOptions) -> (Vec<u8>, Gradient) {
+    if width == 0 || height == 0 { return (Vec::new(), Gradient::default()); }
+    let plane: Vec<f32> = input.iter().map(|&p| p as f32).collect();
+    let smoothed = gaussian_blur(&plane, width, height, options.sigma);
+    let gradient = plane_gradient(&smoothed, width, height, options.detector);
+    let edges = match options.detector {
+        EdgeDetector::Sobel | EdgeDetector::Scharr | EdgeDetector::Prewitt => {
+            gradient.magnitude.iter().map(|&m| if m > options.threshold { 255 } else { 0 }).collect()
+        }
+        EdgeDetector::LaplacianOfGaussian => laplacian_zero_crossings(&smoothed, &gradient, width, height, options.threshold),
+        EdgeDetector::Canny => {
+            let low = options.low_threshold.min(options.threshold);
+            canny(&gradient, width, height, low, options.threshold)
+        }
+    };
+    (edges, gradient)
+}
+
 // Sobel edge detection algorithm
 pub fn sobel_edge_detection(
     input_ptr: *const u8,
@@ -9,50 +233,140 @@ pub fn sobel_edge_detection(
     // Convert the input and output pointers to slices
     let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
     let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
-    
-    // Sobel operators
-    const SOBEL_X: [[i32; 3]; 3] = [
-        [-1, 0, 1],
-        [-2, 0, 2],
-        [-1, 0, 1],
-    ];
-    
-    const SOBEL_Y: [[i32; 3]; 3] = [
-        [-1, -2, -1],
-        [0, 0, 0],
-        [1, 2, 1],
+
+    // The threshold applies to the unscaled Sobel response, four times the normalized magnitude
+    let gradient = gradient(input, width, height, EdgeDetector::Sobel, 0.0);
+    for (out, &m) in output.iter_mut().zip(&gradient.magnitude) {
+        *out = if 4.0 * m > threshold as f32 { 255 } else { 0 };
+    }
+}
+
+// Edge detection with any detector; one byte per pixel output. The gradient
+// magnitude and orientation (f32 per pixel) are written when their pointers are not null.
+pub fn detect_edges(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    options: &EdgeOptions,
+    output_ptr: *mut u8,
+    magnitude_ptr: *mut f32,
+    orientation_ptr: *mut f32,
+) {
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+    let (edges, gradient) = find_edges(input, width, height, options);
+    output.copy_from_slice(&edges);
+    if !magnitude_ptr.is_null() {
+        unsafe { std::slice::from_raw_parts_mut(magnitude_ptr, size) }.copy_from_slice(&gradient.magnitude);
+    }
+    if !orientation_ptr.is_null() {
+        unsafe { std::slice::from_raw_parts_mut(orientation_ptr, size) }.copy_from_slice(&gradient.orientation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_DETECTORS: [EdgeDetector; 5] = [
+        EdgeDetector::Sobel,
+        EdgeDetector::Scharr,
+        EdgeDetector::Prewitt,
+        EdgeDetector::LaplacianOfGaussian,
+        EdgeDetector::Canny,
     ];
-    
-    // Initialize output to black
-    for i in 0..output.len() {
-        output[i] = 0;
-    }
-    
-    // Apply Sobel operator
-    for y in 1..height-1 {
-        for x in 1..width-1 {
-            let mut gx = 0;
-            let mut gy = 0;
-            
-            // Apply convolution
-            for ky in 0..3 {
-                for kx in 0..3 {
-                    let pixel_y = y + ky - 1;
-                    let pixel_x = x + kx - 1;
-                    let idx = pixel_y * width + pixel_x;
-                    let pixel_value = input[idx] as i32;
-                    
-                    gx += pixel_value * SOBEL_X[ky][kx];
-                    gy += pixel_value * SOBEL_Y[ky][kx];
+
+    // `low` left of column `edge`, `high` from there on
+    fn vertical_step(width: usize, height: usize, edge: usize, low: u8, high: u8) -> Vec<u8> {
+        (0..width * height).map(|i| if i % width < edge { low } else { high }).collect()
+    }
+
+    fn transpose(plane: &[u8], width: usize, height: usize) -> Vec<u8> {
+        (0..width * height).map(|i| plane[(i % height) * width + i / height]).collect()
+    }
+
+    #[test]
+    fn a_full_step_has_magnitude_255_for_every_operator() {
+        let (width, height) = (16, 12);
+        let step = vertical_step(width, height, 8, 0, 255);
+        for detector in [EdgeDetector::Sobel, EdgeDetector::Scharr, EdgeDetector::Prewitt] {
+            let across = gradient(&step, width, height, detector, 0.0);
+            let down = gradient(&transpose(&step, width, height), height, width, detector, 0.0);
+            for y in 0..height {
+                for x in [7, 8] {
+                    let i = y * width + x;
+                    assert!((across.magnitude[i] - 255.0).abs() < 1e-3, "{:?} at ({}, {})", detector, x, y);
+                    assert!(across.orientation[i].abs() < 1e-6);
+                    let j = x * height + y;
+                    assert!((down.magnitude[j] - 255.0).abs() < 1e-3);
+                    assert!((down.orientation[j] - PI / 2.0).abs() < 1e-6);
+                }
+                assert_eq!(across.magnitude[y * width], 0.0, "clamped border");
+            }
+        }
+    }
+
+    #[test]
+    fn flat_images_have_no_edges() {
+        let (width, height) = (9, 7);
+        for detector in ALL_DETECTORS {
+            let options = EdgeOptions { detector, threshold: 0.5, low_threshold: 0.1, ..EdgeOptions::default() };
+            let (edges, gradient) = find_edges(&vec![140; width * height], width, height, &options);
+            assert!(edges.iter().all(|&e| e == 0), "{:?}", detector);
+            assert!(gradient.magnitude.iter().all(|&m| m.abs() < 1e-3), "{:?}", detector);
+        }
+    }
+
+    #[test]
+    fn thinning_detectors_draw_one_pixel_per_row_across_a_soft_step() {
+        let (width, height) = (24, 10);
+        for edge in [11, 12] {
+            let step = vertical_step(width, height, edge, 30, 220);
+            for detector in [EdgeDetector::Canny, EdgeDetector::LaplacianOfGaussian] {
+                let options = EdgeOptions { detector, sigma: 1.4, ..EdgeOptions::default() };
+                let (edges, _) = find_edges(&step, width, height, &options);
+                for row in edges.chunks_exact(width) {
+                    let columns: Vec<usize> = (0..width).filter(|&x| row[x] == 255).collect();
+                    assert_eq!(columns.len(), 1, "{:?} edge at {}: {:?}", detector, edge, columns);
+                    assert!(columns[0].abs_diff(edge) <= 1, "{:?} edge at {}: {:?}", detector, edge, columns);
                 }
             }
-            
-            // Compute gradient magnitude
-            let mag = ((gx * gx + gy * gy) as f32).sqrt();
-            
-            // Apply threshold
-            let idx = y * width + x;
-            output[idx] = if mag > threshold as f32 { 255 } else { 0 };
         }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn canny_keeps_weak_ridges_only_when_they_touch_strong_ones() {
+        // Between the thresholds on its own, the weak step is dropped
+        let (width, height) = (20, 20);
+        let options = EdgeOptions { detector: EdgeDetector::Canny, threshold: 64.0, low_threshold: 32.0, sigma: 0.0 };
+        let weak = vertical_step(width, height, 10, 0, 48);
+        assert!(find_edges(&weak, width, height, &options).0.iter().all(|&e| e == 0));
+
+        // Continuing a strong step, it is traced all the way down (rows at the
+        // junction bend along the horizontal edge between the two strengths)
+        let mut joined = weak.clone();
+        joined[..width * height / 2].copy_from_slice(&vertical_step(width, height / 2, 10, 0, 255));
+        let (edges, _) = find_edges(&joined, width, height, &options);
+        for (y, row) in edges.chunks_exact(width).enumerate().filter(|&(y, _)| !(9..=10).contains(&y)) {
+            assert!(row[9] == 255 || row[10] == 255, "row {}", y);
+        }
+    }
+
+    #[test]
+    fn blur_keeps_flat_planes_and_the_mean_of_symmetric_ones() {
+        let (width, height) = (11, 9);
+        let flat = vec![77.0; width * height];
+        assert!(gaussian_blur(&flat, width, height, 2.0).iter().all(|&v| (v - 77.0).abs() < 1e-3));
+        assert_eq!(gaussian_blur(&flat, width, height, 0.0), flat);
+
+        // A centered spike spreads out but keeps most of its mass and its center stays the maximum
+        let mut spike = vec![0.0; width * height];
+        spike[4 * width + 5] = 1000.0;
+        let blurred = gaussian_blur(&spike, width, height, 1.0);
+        assert!((blurred.iter().sum::<f32>() - 1000.0).abs() < 1.0);
+        let peak = blurred.iter().cloned().fold(0.0, f32::max);
+        assert_eq!(peak, blurred[4 * width + 5]);
+        assert!(peak < 200.0);
+    }
+}
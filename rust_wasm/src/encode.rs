@@ -0,0 +1,498 @@
+use wasm_bindgen::prelude::*;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use std::collections::HashMap;
+
+// Bit-depth-correct image encoders
+//
+// Dithered output only holds a handful of distinct colors, so it is first
+// reduced to a palette plus one index per pixel. The encoders then write that
+// at the smallest depth the format allows: PNG as 1/2/4/8-bit grayscale or
+// indexed (PLTE) and BMP as 1/4/8 bpp with a color table. Images with more
+// than 256 colors fall back to truecolor PNG; BMP output stays paletted.
+// Netpbm has no palette, so PBM/PGM/PPM samples are written directly.
+
+// zlib level used for PNG image data
+const PNG_COMPRESSION_LEVEL: u8 = 9;
+
+// How PNG pixels are stored
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngColorMode {
+    Auto,      // Grayscale when the palette is gray, indexed otherwise
+    Grayscale, // Luma only, at the smallest exact bit depth
+    Indexed,   // PLTE palette, 1/2/4/8-bit indices
+}
+
+// Netpbm flavor
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetpbmFormat {
+    Pbm, // P4, 1 bit per pixel, 1 = black
+    Pgm, // P5, 8-bit gray
+    Ppm, // P6, 8-bit RGB
+}
+
+// An image as a color table plus one palette index per pixel
+#[derive(Clone, Debug)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub palette: Vec<[u8; 3]>,
+    pub indices: Vec<u8>,
+}
+
+impl IndexedImage {
+    // Build from pixels with `channels` bytes each (1 = gray, 3 = RGB, 4 = RGBA; alpha is ignored).
+    // Returns None for any other channel count or when the image has more than 256 colors.
+    pub fn from_pixels(pixels: &[u8], width: usize, height: usize, channels: usize) -> Option<IndexedImage> {
+        if !valid_channels(channels) { return None; }
+        let colors: Vec<[u8; 3]> = pixels
+            .chunks_exact(channels)
+            .take(width * height)
+            .map(|p| if channels >= 3 { [p[0], p[1], p[2]] } else { [p[0]; 3] })
+            .collect();
+
+        // Palette sorted dark to light, so gray palettes come out in tone order
+        let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+        for color in &colors {
+            if !lookup.contains_key(color) {
+                if lookup.len() == 256 { return None; }
+                lookup.insert(*color, 0);
+            }
+        }
+        let mut palette: Vec<[u8; 3]> = lookup.keys().copied().collect();
+        palette.sort_by_key(|c| (luma(*c), *c));
+        for (i, color) in palette.iter().enumerate() {
+            lookup.insert(*color, i as u8);
+        }
+
+        let indices = colors.iter().map(|c| lookup[c]).collect();
+        Some(IndexedImage { width, height, palette, indices })
+    }
+
+    fn is_gray(&self) -> bool {
+        self.palette.iter().all(|c| c[0] == c[1] && c[1] == c[2])
+    }
+}
+
+// Pixel layouts the encoders accept: gray, RGB or RGBA
+fn valid_channels(channels: usize) -> bool {
+    matches!(channels, 1 | 3 | 4)
+}
+
+// Rec. 601 luma, matching the grayscale conversion used by the editor
+fn luma(color: [u8; 3]) -> u8 {
+    (0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32).round() as u8
+}
+
+// Smallest of 1/2/4/8 bits that can index `count` entries
+fn index_bit_depth(count: usize) -> u8 {
+    match count {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    }
+}
+
+// Smallest of 1/2/4/8 bits whose evenly spaced levels contain every gray value
+fn gray_bit_depth(values: &[u8]) -> u8 {
+    for depth in [1u8, 2, 4] {
+        let max = (1u32 << depth) - 1;
+        let exact = values.iter().all(|&v| (v as u32 * max).is_multiple_of(255));
+        if exact { return depth; }
+    }
+    8
+}
+
+// Pack samples of `depth` bits into rows, most significant bits first
+fn pack_rows(samples: &[u8], width: usize, height: usize, depth: u8) -> Vec<Vec<u8>> {
+    let depth = depth as usize;
+    let row_bytes = (width * depth).div_ceil(8);
+    (0..height)
+        .map(|y| {
+            let mut row = vec![0u8; row_bytes];
+            for x in 0..width {
+                let bit = x * depth;
+                row[bit / 8] |= samples[y * width + x] << (8 - depth - bit % 8);
+            }
+            row
+        })
+        .collect()
+}
+
+// ----- PNG -----
+
+// CRC-32 (ISO 3309) as used by PNG chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xEDB8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+// Apply PNG filter `kind` to one row; `bpp` is the byte distance to the left neighbor
+fn filter_row(kind: u8, row: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    out.push(kind);
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = prev[i];
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let predicted = match kind {
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => 0,
+        };
+        out.push(row[i].wrapping_sub(predicted));
+    }
+}
+
+// Filter rows and compress them. Sub-byte rows stay unfiltered (the usual best
+// choice for packed bits); 8-bit rows pick the filter with the smallest
+// sum of absolute differences.
+fn png_image_data(rows: &[Vec<u8>], depth: u8, bytes_per_pixel: usize) -> Vec<u8> {
+    let row_bytes = rows.first().map_or(0, |r| r.len());
+    let zero_row = vec![0u8; row_bytes];
+    let mut raw = Vec::with_capacity(rows.len() * (row_bytes + 1));
+    let mut candidate = Vec::with_capacity(row_bytes + 1);
+
+    for (y, row) in rows.iter().enumerate() {
+        let prev = if y > 0 { &rows[y - 1] } else { &zero_row };
+        if depth < 8 {
+            filter_row(0, row, prev, 1, &mut raw);
+            continue;
+        }
+        let mut best: Option<(u64, Vec<u8>)> = None;
+        for kind in 0..5 {
+            candidate.clear();
+            filter_row(kind, row, prev, bytes_per_pixel, &mut candidate);
+            let cost: u64 = candidate[1..].iter().map(|&v| (v as i8).unsigned_abs() as u64).sum();
+            if best.as_ref().is_none_or(|(c, _)| cost < *c) {
+                best = Some((cost, candidate.clone()));
+            }
+        }
+        if let Some((_, filtered)) = best {
+            raw.extend_from_slice(&filtered);
+        }
+    }
+    compress_to_vec_zlib(&raw, PNG_COMPRESSION_LEVEL)
+}
+
+fn png_header(png: &mut Vec<u8>, width: usize, height: usize, depth: u8, color_type: u8) {
+    png.extend_from_slice(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // Depth, color type, deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[depth, color_type, 0, 0, 0]);
+    write_chunk(png, b"IHDR", &ihdr);
+}
+
+// Encode an indexed image as PNG
+pub fn encode_png(image: &IndexedImage, mode: PngColorMode) -> Vec<u8> {
+    let (width, height) = (image.width, image.height);
+    let grayscale = match mode {
+        PngColorMode::Auto => image.is_gray(),
+        PngColorMode::Grayscale => true,
+        PngColorMode::Indexed => false,
+    };
+
+    let mut png = Vec::new();
+    if grayscale {
+        let grays: Vec<u8> = image.palette.iter().map(|&c| luma(c)).collect();
+        let depth = gray_bit_depth(&grays);
+        let max = (1u32 << depth) - 1;
+        let levels: Vec<u8> = grays.iter().map(|&g| ((g as u32 * max + 127) / 255) as u8).collect();
+        let samples: Vec<u8> = image.indices.iter().map(|&i| levels[i as usize]).collect();
+
+        png_header(&mut png, width, height, depth, 0);
+        let rows = pack_rows(&samples, width, height, depth);
+        write_chunk(&mut png, b"IDAT", &png_image_data(&rows, depth, 1));
+    } else {
+        let depth = index_bit_depth(image.palette.len());
+        png_header(&mut png, width, height, depth, 3);
+        let plte: Vec<u8> = image.palette.iter().flatten().copied().collect();
+        write_chunk(&mut png, b"PLTE", &plte);
+        let rows = pack_rows(&image.indices, width, height, depth);
+        write_chunk(&mut png, b"IDAT", &png_image_data(&rows, depth, 1));
+    }
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// Encode RGB pixels as 8-bit truecolor PNG, for images with more than 256 colors
+pub fn encode_png_truecolor(rgb: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut png = Vec::new();
+    png_header(&mut png, width, height, 8, 2);
+    let rows: Vec<Vec<u8>> = rgb.chunks_exact(width * 3).take(height).map(|r| r.to_vec()).collect();
+    write_chunk(&mut png, b"IDAT", &png_image_data(&rows, 8, 3));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// ----- Netpbm -----
+
+// Encode `channels`-byte pixels (1 = gray, 3 = RGB, 4 = RGBA) as binary Netpbm.
+// Samples are written directly, so any number of colors fits. PBM marks pixels
+// darker than mid gray as black.
+pub fn encode_netpbm_samples(pixels: &[u8], width: usize, height: usize, channels: usize, format: NetpbmFormat) -> Vec<u8> {
+    if !valid_channels(channels) { return Vec::new(); }
+    let (magic, max_value) = match format {
+        NetpbmFormat::Pbm => ("P4", ""),
+        NetpbmFormat::Pgm => ("P5", "\n255"),
+        NetpbmFormat::Ppm => ("P6", "\n255"),
+    };
+    let mut out = format!("{}\n{} {}{}\n", magic, width, height, max_value).into_bytes();
+
+    let colors = pixels
+        .chunks_exact(channels)
+        .take(width * height)
+        .map(|p| if channels >= 3 { [p[0], p[1], p[2]] } else { [p[0]; 3] });
+    match format {
+        NetpbmFormat::Pbm => {
+            let bits: Vec<u8> = colors.map(|c| (luma(c) < 128) as u8).collect();
+            for row in pack_rows(&bits, width, height, 1) {
+                out.extend_from_slice(&row);
+            }
+        }
+        NetpbmFormat::Pgm if channels == 1 => out.extend(pixels.iter().take(width * height)),
+        NetpbmFormat::Pgm => out.extend(colors.map(luma)),
+        NetpbmFormat::Ppm => out.extend(colors.flatten()),
+    }
+    out
+}
+
+// ----- BMP -----
+
+// Encode as an uncompressed bottom-up BMP with a 1, 4 or 8 bpp color table
+pub fn encode_bmp(image: &IndexedImage) -> Vec<u8> {
+    let (width, height) = (image.width, image.height);
+    let depth = match index_bit_depth(image.palette.len()) {
+        1 => 1,
+        2 | 4 => 4, // BMP has no 2 bpp
+        _ => 8,
+    };
+    let colors = 1usize << depth;
+    let row_bytes = (width * depth as usize).div_ceil(32) * 4;
+    let table_size = colors * 4;
+    let data_offset = 14 + 40 + table_size;
+    let file_size = data_offset + row_bytes * height;
+
+    let mut bmp = Vec::with_capacity(file_size);
+    // BITMAPFILEHEADER
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(file_size as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(data_offset as u32).to_le_bytes());
+    // BITMAPINFOHEADER
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    bmp.extend_from_slice(&(height as i32).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&(depth as u16).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes()); // BI_RGB
+    bmp.extend_from_slice(&((row_bytes * height) as u32).to_le_bytes());
+    bmp.extend_from_slice(&2835u32.to_le_bytes()); // 72 DPI
+    bmp.extend_from_slice(&2835u32.to_le_bytes());
+    bmp.extend_from_slice(&(colors as u32).to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    // Color table, BGR0
+    for i in 0..colors {
+        let [r, g, b] = image.palette.get(i).copied().unwrap_or([0, 0, 0]);
+        bmp.extend_from_slice(&[b, g, r, 0]);
+    }
+    // Pixel rows, bottom-up, padded to four bytes
+    let rows = pack_rows(&image.indices, width, height, depth);
+    for row in rows.iter().rev() {
+        bmp.extend_from_slice(row);
+        bmp.resize(bmp.len() + row_bytes - row.len(), 0);
+    }
+    bmp
+}
+
+// Encode `channels`-byte pixels (1 = gray, 3 = RGB, 4 = RGBA) as PNG, falling back to
+// truecolor when there are more than 256 colors; empty for other channel counts
+pub fn encode_png_pixels(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    channels: usize,
+    mode: PngColorMode,
+) -> Vec<u8> {
+    if !valid_channels(channels) { return Vec::new(); }
+    let pixels = unsafe { std::slice::from_raw_parts(input_ptr, width * height * channels) };
+    match IndexedImage::from_pixels(pixels, width, height, channels) {
+        Some(image) => encode_png(&image, mode),
+        None => {
+            // More than 256 colors needs at least three channels
+            let rgb: Vec<u8> = pixels.chunks_exact(channels).flat_map(|p| [p[0], p[1], p[2]]).collect();
+            encode_png_truecolor(&rgb, width, height)
+        }
+    }
+}
+
+// Encode `channels`-byte pixels as Netpbm; empty for channel counts other than 1, 3 or 4
+pub fn encode_netpbm_pixels(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    channels: usize,
+    format: NetpbmFormat,
+) -> Vec<u8> {
+    if !valid_channels(channels) { return Vec::new(); }
+    let pixels = unsafe { std::slice::from_raw_parts(input_ptr, width * height * channels) };
+    encode_netpbm_samples(pixels, width, height, channels, format)
+}
+
+// Encode `channels`-byte pixels as a paletted BMP; empty when there are more than 256
+// colors or for channel counts other than 1, 3 or 4
+pub fn encode_bmp_pixels(input_ptr: *const u8, width: usize, height: usize, channels: usize) -> Vec<u8> {
+    if !valid_channels(channels) { return Vec::new(); }
+    let pixels = unsafe { std::slice::from_raw_parts(input_ptr, width * height * channels) };
+    match IndexedImage::from_pixels(pixels, width, height, channels) {
+        Some(image) => encode_bmp(&image),
+        None => Vec::new(),
+    }
+}
+
+// Round trips through the native decoder (the `image` crate)
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::decode::decode_image;
+
+    // Deterministic test pixels drawn from `colors` distinct colors
+    fn pixels(width: usize, height: usize, channels: usize, colors: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let c = (i * 7 + i / width) % colors;
+                let rgb = [(c * 37 % 256) as u8, (c * 101 / 3 % 256) as u8, (c / 256 * 64 + c % 7 * 30) as u8];
+                match channels {
+                    1 => vec![(c * 255 / (colors - 1).max(1)) as u8],
+                    3 => rgb.to_vec(),
+                    _ => vec![rgb[0], rgb[1], rgb[2], (i % 256) as u8],
+                }
+            })
+            .collect()
+    }
+
+    // What the decoder should return: opaque RGBA, alpha ignored
+    fn expected_rgba(pixels: &[u8], channels: usize) -> Vec<u8> {
+        pixels
+            .chunks_exact(channels)
+            .flat_map(|p| if channels == 1 { [p[0], p[0], p[0], 255] } else { [p[0], p[1], p[2], 255] })
+            .collect()
+    }
+
+    fn assert_decodes_to(bytes: &[u8], width: usize, height: usize, rgba: &[u8], what: &str) {
+        let image = decode_image(bytes).unwrap_or_else(|err| panic!("{}: {}", what, err));
+        assert_eq!((image.width, image.height), (width, height), "{}", what);
+        assert!(image.rgba == rgba, "{}: pixels differ", what);
+    }
+
+    #[test]
+    fn png_round_trips() {
+        for (width, height) in [(1, 1), (13, 5), (64, 9)] {
+            for channels in [1, 3, 4] {
+                for colors in [2, 3, 16, 200, 1000] {
+                    if channels == 1 && colors > 256 { continue; }
+                    let data = pixels(width, height, channels, colors);
+                    let expected = expected_rgba(&data, channels);
+                    for mode in [PngColorMode::Auto, PngColorMode::Indexed] {
+                        let png = encode_png_pixels(data.as_ptr(), width, height, channels, mode);
+                        let what = format!("PNG {}x{} {} channels {} colors {:?}", width, height, channels, colors, mode);
+                        assert_decodes_to(&png, width, height, &expected, &what);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn png_grayscale_stores_luma() {
+        let data = pixels(11, 7, 3, 5);
+        let png = encode_png_pixels(data.as_ptr(), 11, 7, 3, PngColorMode::Grayscale);
+        let expected: Vec<u8> = data.chunks_exact(3).flat_map(|p| { let l = luma([p[0], p[1], p[2]]); [l, l, l, 255] }).collect();
+        assert_decodes_to(&png, 11, 7, &expected, "grayscale PNG");
+    }
+
+    #[test]
+    fn bmp_round_trips_with_row_padding() {
+        // Odd widths leave padding at the end of every row at 1, 4 and 8 bpp
+        for (width, height) in [(1, 1), (7, 3), (13, 5), (33, 4)] {
+            for channels in [1, 3, 4] {
+                for colors in [2, 9, 200] {
+                    let data = pixels(width, height, channels, colors);
+                    let bmp = encode_bmp_pixels(data.as_ptr(), width, height, channels);
+                    let what = format!("BMP {}x{} {} channels {} colors", width, height, channels, colors);
+                    assert_decodes_to(&bmp, width, height, &expected_rgba(&data, channels), &what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bmp_refuses_more_than_256_colors() {
+        let data = pixels(40, 10, 3, 300);
+        assert!(encode_bmp_pixels(data.as_ptr(), 40, 10, 3).is_empty());
+    }
+
+    #[test]
+    fn netpbm_round_trips_any_number_of_colors() {
+        let (width, height) = (13, 6);
+        for channels in [1, 3, 4] {
+            let data = pixels(width, height, channels, if channels == 1 { 78 } else { 78 * 4 });
+            let rgb: Vec<[u8; 3]> = data
+                .chunks_exact(channels)
+                .map(|p| if channels == 1 { [p[0]; 3] } else { [p[0], p[1], p[2]] })
+                .collect();
+
+            let ppm = encode_netpbm_pixels(data.as_ptr(), width, height, channels, NetpbmFormat::Ppm);
+            let expected: Vec<u8> = rgb.iter().flat_map(|c| [c[0], c[1], c[2], 255]).collect();
+            assert_decodes_to(&ppm, width, height, &expected, "PPM");
+
+            let pgm = encode_netpbm_pixels(data.as_ptr(), width, height, channels, NetpbmFormat::Pgm);
+            let expected: Vec<u8> = rgb.iter().flat_map(|&c| { let l = luma(c); [l, l, l, 255] }).collect();
+            assert_decodes_to(&pgm, width, height, &expected, "PGM");
+
+            let pbm = encode_netpbm_pixels(data.as_ptr(), width, height, channels, NetpbmFormat::Pbm);
+            let expected: Vec<u8> = rgb.iter().flat_map(|&c| { let v = if luma(c) < 128 { 0 } else { 255 }; [v, v, v, 255] }).collect();
+            assert_decodes_to(&pbm, width, height, &expected, "PBM");
+        }
+    }
+
+    #[test]
+    fn invalid_channel_counts_are_empty() {
+        let data = [0u8; 64];
+        for channels in [0, 2, 5] {
+            assert!(encode_png_pixels(data.as_ptr(), 2, 2, channels, PngColorMode::Auto).is_empty());
+            assert!(encode_bmp_pixels(data.as_ptr(), 2, 2, channels).is_empty());
+            assert!(encode_netpbm_pixels(data.as_ptr(), 2, 2, channels, NetpbmFormat::Pgm).is_empty());
+        }
+    }
+}
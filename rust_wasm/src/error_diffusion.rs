@@ -0,0 +1,301 @@
+use wasm_bindgen::prelude::*;
+
+use crate::edge_detection::{gaussian_blur, plane_gradient, EdgeDetector};
+use crate::scan_path::{scan_path, ScanPath};
+
+// Error diffusion along any scan path
+//
+// The classic kernels are written for a raster scan: dx points along the row
+// (the direction of travel) and dy down to the rows still to come. Along other
+// paths each kernel is turned to match the step being taken: "forward" is the
+// direction to the next pixel on the path, and "down" is whichever
+// perpendicular has more of the kernel landing on unvisited pixels. Weights
+// that would land on already-visited or outside pixels are dropped and the
+// rest scaled up, so each pixel still passes on the kernel's full share of its
+// error (all of it, except for Atkinson's 6/8).
+//
+// The edge-aware mode smooths the input and takes its gradient with the
+// operators in edge_detection.rs. A tap whose target differs from the source
+// by a full edge step is damped, and the weight it loses goes to the taps
+// running along the edge (perpendicular to the gradient), so error stays on its
+// own side: strokes and text keep their tone and light surroundings get no
+// halo. An optional unsharp-mask pass, scaled by gradient strength, steepens
+// the edges first so near-black strokes come out solid.
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffusionKernel {
+    FloydSteinberg = 0,
+    Atkinson = 1,
+    SierraLite = 2,
+    Burkes = 3,
+    Stucki = 4,
+    JarvisJudiceNinke = 5,
+}
+
+// Kernels as (dx, dy, weight) with their divisor
+const FLOYD_STEINBERG: (&[(isize, usize, f32)], f32) = (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0);
+// Atkinson diffuses only 6/8 of the error
+const ATKINSON: (&[(isize, usize, f32)], f32) =
+    (&[(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)], 8.0);
+const SIERRA_LITE: (&[(isize, usize, f32)], f32) = (&[(1, 0, 2.0), (-1, 1, 1.0), (0, 1, 1.0)], 4.0);
+const BURKES: (&[(isize, usize, f32)], f32) = (
+    &[(1, 0, 8.0), (2, 0, 4.0), (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0)],
+    32.0,
+);
+const STUCKI: (&[(isize, usize, f32)], f32) = (
+    &[
+        (1, 0, 8.0), (2, 0, 4.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+        (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+    ],
+    42.0,
+);
+const JARVIS_JUDICE_NINKE: (&[(isize, usize, f32)], f32) = (
+    &[
+        (1, 0, 7.0), (2, 0, 5.0),
+        (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+        (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+    ],
+    48.0,
+);
+
+impl DiffusionKernel {
+    // Raster-oriented (dx, dy, weight) taps and the divisor
+    pub fn weights(self) -> (&'static [(isize, usize, f32)], f32) {
+        match self {
+            DiffusionKernel::FloydSteinberg => FLOYD_STEINBERG,
+            DiffusionKernel::Atkinson => ATKINSON,
+            DiffusionKernel::SierraLite => SIERRA_LITE,
+            DiffusionKernel::Burkes => BURKES,
+            DiffusionKernel::Stucki => STUCKI,
+            DiffusionKernel::JarvisJudiceNinke => JARVIS_JUDICE_NINKE,
+        }
+    }
+}
+
+// Unit step from a to b, or None when the path jumps
+fn step_direction(a: (usize, usize), b: (usize, usize)) -> Option<(isize, isize)> {
+    let dx = b.0 as isize - a.0 as isize;
+    let dy = b.1 as isize - a.1 as isize;
+    if dx.abs() + dy.abs() == 1 { Some((dx, dy)) } else { None }
+}
+
+// The smoothed input with its gradient, steering edge-aware diffusion
+struct EdgeField {
+    smoothed: Vec<f32>,
+    strength: Vec<f32>, // Gradient magnitude relative to a full-strength edge, 0 to 1
+    orientation: Vec<f32>,
+    edge_threshold: f32,
+    damping: f32,
+}
+
+impl EdgeField {
+    fn new(input: &[u8], width: usize, height: usize, options: &EdgeAwareOptions) -> Self {
+        let plane: Vec<f32> = input.iter().map(|&p| p as f32).collect();
+        let smoothed = gaussian_blur(&plane, width, height, options.sigma);
+        let gradient = plane_gradient(&smoothed, width, height, options.detector);
+        let edge_threshold = options.edge_threshold.max(1e-3);
+        EdgeField {
+            strength: gradient.magnitude.iter().map(|&m| (m / edge_threshold).min(1.0)).collect(),
+            orientation: gradient.orientation,
+            smoothed,
+            edge_threshold,
+            damping: options.damping.clamp(0.0, 1.0),
+        }
+    }
+
+    // How much of a tap from pixel i to pixel n crosses an edge (0 to 1), from
+    // the step in the smoothed input between them, and how much the tap runs
+    // along the edge at i (0 to 1)
+    fn crossing(&self, i: usize, n: usize, offset: (isize, isize)) -> (f32, f32) {
+        let step = (self.smoothed[n] - self.smoothed[i]).abs() / self.edge_threshold;
+        let (sin, cos) = self.orientation[i].sin_cos();
+        let length = ((offset.0 * offset.0 + offset.1 * offset.1) as f32).sqrt();
+        let across = ((offset.0 as f32 * cos + offset.1 as f32 * sin) / length).abs();
+        (self.damping * step.min(1.0), 1.0 - across)
+    }
+}
+
+// Settings for edge-aware diffusion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeAwareOptions {
+    pub detector: EdgeDetector, // Gradient operator (LoG and Canny use Sobel)
+    pub sigma: f32,             // Smoothing before the gradient, so noise does not read as edges
+    pub edge_threshold: f32,    // Gray-level step (and gradient magnitude) treated as a full-strength edge
+    pub damping: f32,           // 0 diffuses as usual, 1 stops error crossing full-strength edges
+    pub sharpen: f32,           // Unsharp-mask amount applied at edges before diffusion; 0 for none
+}
+
+impl Default for EdgeAwareOptions {
+    fn default() -> Self {
+        EdgeAwareOptions { detector: EdgeDetector::Sobel, sigma: 0.7, edge_threshold: 64.0, damping: 1.0, sharpen: 0.0 }
+    }
+}
+
+// Unsharp mask scaled by edge strength, so edges get crisper while flat areas and their noise are left alone
+fn sharpen_edges(input: &[u8], width: usize, height: usize, edges: &EdgeField, amount: f32) -> Vec<f32> {
+    let plane: Vec<f32> = input.iter().map(|&p| p as f32).collect();
+    let blurred = gaussian_blur(&plane, width, height, 1.0);
+    plane
+        .iter()
+        .zip(&blurred)
+        .zip(&edges.strength)
+        .map(|((&p, &b), &e)| (p + amount * e * (p - b)).clamp(0.0, 255.0))
+        .collect()
+}
+
+// Diffusion core shared by the plain and edge-aware entry points
+fn diffuse(
+    mut buffer: Vec<f32>,
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    edges: Option<&EdgeField>,
+) -> Vec<u8> {
+    let size = width * height;
+    let mut output = vec![0u8; size];
+    let mut visited = vec![false; size];
+    let (taps, divisor) = kernel.weights();
+    let share = taps.iter().map(|t| t.2).sum::<f32>() / divisor; // Fraction of the error passed on
+    let threshold_f = threshold as f32;
+
+    let points = scan_path(path, width, height);
+    let mut forward = (1, 0);
+    // (pixel, weight, image offset) of the taps on each perpendicular
+    let mut targets: Vec<(usize, f32, (isize, isize))> = Vec::with_capacity(taps.len());
+    let mut mirrored_targets: Vec<(usize, f32, (isize, isize))> = Vec::with_capacity(taps.len());
+    let mut shares: Vec<f32> = Vec::with_capacity(taps.len());
+    let mut along: Vec<f32> = Vec::with_capacity(taps.len());
+    for (step, &(x, y)) in points.iter().enumerate() {
+        let i = y * width + x;
+        visited[i] = true;
+        let value = buffer[i];
+        let new_value = if value < threshold_f { 0.0 } else { 255.0 };
+        output[i] = new_value as u8;
+        let error = value - new_value;
+
+        // Jumps (row ends, Z-order, clipped curves) and the last pixel keep the previous direction
+        if let Some(direction) = points.get(step + 1).and_then(|&next| step_direction((x, y), next)) {
+            forward = direction;
+        }
+
+        // Kernel taps for one of the two perpendiculars, on unvisited pixels only
+        let collect = |side: (isize, isize), targets: &mut Vec<(usize, f32, (isize, isize))>| {
+            targets.clear();
+            for &(dx, dy, weight) in taps {
+                let offset = (dx * forward.0 + dy as isize * side.0, dx * forward.1 + dy as isize * side.1);
+                let (nx, ny) = (x as isize + offset.0, y as isize + offset.1);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { continue; }
+                let n = ny as usize * width + nx as usize;
+                if !visited[n] {
+                    targets.push((n, weight, offset));
+                }
+            }
+            targets.iter().map(|t| t.1).sum::<f32>()
+        };
+        let side = (-forward.1, forward.0);
+        let mut kept = collect(side, &mut targets);
+        let mirrored_kept = collect((forward.1, -forward.0), &mut mirrored_targets);
+        if mirrored_kept > kept {
+            std::mem::swap(&mut targets, &mut mirrored_targets);
+            kept = mirrored_kept;
+        }
+        if kept <= 0.0 { continue; }
+
+        shares.clear();
+        shares.extend(targets.iter().map(|t| t.1));
+        if let Some(edges) = edges {
+            // Damp taps crossing an edge and hand what they lose to the taps running along it;
+            // with nowhere along the edge to go, that error is dropped
+            let mut lost = 0.0;
+            let mut along_total = 0.0;
+            along.clear();
+            for (k, &(n, weight, offset)) in targets.iter().enumerate() {
+                let (crossing, parallel) = edges.crossing(i, n, offset);
+                lost += weight * crossing;
+                shares[k] = weight * (1.0 - crossing);
+                along.push(shares[k] * parallel);
+                along_total += along[k];
+            }
+            if along_total > 1e-6 {
+                for (k, s) in shares.iter_mut().enumerate() {
+                    *s += lost * along[k] / along_total;
+                }
+            }
+        }
+        for (&(n, _, _), &weight) in targets.iter().zip(&shares) {
+            buffer[n] += error * share * weight / kept;
+        }
+    }
+    output
+}
+
+// Error diffusion of a grayscale plane along a scan path; one byte per pixel (0 or 255)
+pub fn diffuse_along_path(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+) -> Vec<u8> {
+    let buffer = input.iter().map(|&p| p as f32).collect();
+    diffuse(buffer, width, height, kernel, path, threshold, None)
+}
+
+// Error diffusion that keeps error from crossing edges of the input (see EdgeAwareOptions)
+pub fn diffuse_edge_aware(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    options: &EdgeAwareOptions,
+) -> Vec<u8> {
+    let edges = EdgeField::new(input, width, height, options);
+    let buffer = if options.sharpen > 0.0 {
+        sharpen_edges(input, width, height, &edges, options.sharpen)
+    } else {
+        input.iter().map(|&p| p as f32).collect()
+    };
+    diffuse(buffer, width, height, kernel, path, threshold, Some(&edges))
+}
+
+// Error diffusion with a chosen kernel along a chosen scan path; one byte per pixel output
+pub fn path_diffusion_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    output_ptr: *mut u8,
+) {
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+    output.copy_from_slice(&diffuse_along_path(input, width, height, kernel, path, threshold));
+}
+
+// Edge-aware error diffusion with a chosen kernel and scan path; one byte per pixel output
+#[allow(clippy::too_many_arguments)]
+pub fn edge_aware_diffusion_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    options: &EdgeAwareOptions,
+    output_ptr: *mut u8,
+) {
+    let size = width * height;
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size) };
+    output.copy_from_slice(&diffuse_edge_aware(input, width, height, kernel, path, threshold, options));
+}
@@ -0,0 +1,107 @@
+use wasm_bindgen::prelude::*;
+
+use crate::blue_noise::generate_blue_noise_pattern;
+use crate::void_and_cluster::generate_void_and_cluster_matrix;
+
+// Stochastic (FM) screening with a fixed minimum dot size
+//
+// Offset plates cannot hold single-pixel dots, so the image is screened on a
+// coarser grid of dot_size x dot_size device-pixel cells. Every cell is either
+// a whole ink dot or blank paper; tone only changes how many cells are inked.
+// Cells are switched on against a blue-noise rank mask, which keeps the dots
+// uniformly spread without clumping at any density.
+
+// Side length of the void-and-cluster mask used on the dot grid
+const VOID_AND_CLUSTER_MASK_SIZE: usize = 32;
+
+// Which generator provides the blue-noise rank mask
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FmMaskSource {
+    VoidAndCluster,
+    BlueNoise,
+}
+
+// Mask thresholds (0.0 - 1.0) for every cell of a grid_width x grid_height dot grid
+fn dot_grid_mask(grid_width: usize, grid_height: usize, source: FmMaskSource) -> Vec<f32> {
+    let mut mask = vec![0.0f32; grid_width * grid_height];
+    match source {
+        FmMaskSource::VoidAndCluster => {
+            let matrix = generate_void_and_cluster_matrix(VOID_AND_CLUSTER_MASK_SIZE);
+            for gy in 0..grid_height {
+                for gx in 0..grid_width {
+                    mask[gy * grid_width + gx] = matrix[gy % VOID_AND_CLUSTER_MASK_SIZE][gx % VOID_AND_CLUSTER_MASK_SIZE];
+                }
+            }
+        }
+        FmMaskSource::BlueNoise => {
+            let pattern = generate_blue_noise_pattern(grid_width, grid_height);
+            for (gy, row) in pattern.iter().enumerate() {
+                mask[gy * grid_width..(gy + 1) * grid_width].copy_from_slice(row);
+            }
+        }
+    }
+    mask
+}
+
+// FM screen a grayscale plane; output is 0 where ink dots are placed and 255 elsewhere
+pub fn fm_screen(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    dot_size: usize,
+    source: FmMaskSource,
+    output: &mut [u8],
+) {
+    if width == 0 || height == 0 { return; }
+    let dot_size = dot_size.max(1);
+    let grid_width = width.div_ceil(dot_size);
+    let grid_height = height.div_ceil(dot_size);
+    let mask = dot_grid_mask(grid_width, grid_height, source);
+
+    for gy in 0..grid_height {
+        for gx in 0..grid_width {
+            // The pixels covered by this dot cell (partial at the right and bottom edges)
+            let x0 = gx * dot_size;
+            let y0 = gy * dot_size;
+            let x1 = (x0 + dot_size).min(width);
+            let y1 = (y0 + dot_size).min(height);
+
+            // Average tone under the cell decides its ink coverage
+            let mut sum = 0u32;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    sum += input[y * width + x] as u32;
+                }
+            }
+            let count = ((x1 - x0) * (y1 - y0)) as f32;
+            let coverage = 1.0 - sum as f32 / (count * 255.0);
+
+            // Ink the cell when its coverage beats the mask rank
+            let inked = coverage > mask[gy * grid_width + gx];
+            let value = if inked { 0 } else { 255 };
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    output[y * width + x] = value;
+                }
+            }
+        }
+    }
+}
+
+// Stochastic screening with dot_size x dot_size dots
+pub fn fm_screen_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    dot_size: usize,
+    source: FmMaskSource,
+    output_ptr: *mut u8,
+) {
+    // Convert the input and output pointers to slices
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
+
+    fm_screen(input, width, height, dot_size, source, output);
+}
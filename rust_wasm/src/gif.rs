@@ -0,0 +1,442 @@
+use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
+
+// Animated GIF89a encoder
+//
+// Frames are palette indices, which is what the dithering algorithms produce
+// anyway, so nothing is re-quantized. The encoder writes the logical screen,
+// an optional global palette and NETSCAPE2.0 loop block, then one graphic
+// control extension plus LZW-compressed image per frame (with its own local
+// palette if given).
+
+// Largest LZW code; the table is reset with a clear code when it fills up
+const MAX_LZW_CODE: u16 = 4095;
+
+// What happens to a frame's area before the next frame is drawn
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GifDisposal {
+    Unspecified = 0,
+    Keep = 1,       // Leave the frame in place
+    Background = 2, // Clear the frame area to the background
+    Previous = 3,   // Restore what was there before the frame
+}
+
+// One frame as palette indices covering a rectangle of the logical screen
+#[derive(Clone, Debug)]
+pub struct GifFrame<'a> {
+    pub indices: &'a [u8],
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub delay: u16, // Hundredths of a second
+    pub disposal: GifDisposal,
+    pub transparent_index: Option<u8>,
+    pub local_palette: Option<&'a [[u8; 3]]>,
+}
+
+// Bits needed for a color table holding `colors` entries (GIF tables have 2^n entries, n >= 1)
+fn palette_bits(colors: usize) -> u8 {
+    let mut bits = 1;
+    while (1usize << bits) < colors && bits < 8 {
+        bits += 1;
+    }
+    bits
+}
+
+// Color table padded with black to its power-of-two size
+fn write_palette(out: &mut Vec<u8>, palette: &[[u8; 3]], bits: u8) {
+    for i in 0..(1usize << bits) {
+        out.extend_from_slice(&palette.get(i).copied().unwrap_or([0, 0, 0]));
+    }
+}
+
+// Packs variable-width codes LSB first and writes them as sub-blocks of up to 255 bytes
+struct CodeWriter<'a> {
+    out: &'a mut Vec<u8>,
+    block: Vec<u8>,
+    bits: u32,
+    bit_count: u32,
+}
+
+impl<'a> CodeWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        CodeWriter { out, block: Vec::with_capacity(255), bits: 0, bit_count: 0 }
+    }
+
+    fn write(&mut self, code: u16, width: u8) {
+        self.bits |= (code as u32) << self.bit_count;
+        self.bit_count += width as u32;
+        while self.bit_count >= 8 {
+            self.push_byte(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        self.block.push(byte);
+        if self.block.len() == 255 {
+            self.flush_block();
+        }
+    }
+
+    fn flush_block(&mut self) {
+        if self.block.is_empty() { return; }
+        self.out.push(self.block.len() as u8);
+        self.out.extend_from_slice(&self.block);
+        self.block.clear();
+    }
+
+    fn finish(mut self) {
+        if self.bit_count > 0 {
+            self.push_byte(self.bits as u8);
+        }
+        self.flush_block();
+        self.out.push(0); // Block terminator
+    }
+}
+
+// LZW-compress indices into GIF image data (minimum code size byte included)
+pub fn lzw_encode(indices: &[u8], min_code_size: u8, out: &mut Vec<u8>) {
+    let min_code_size = min_code_size.max(2);
+    out.push(min_code_size);
+
+    let clear_code = 1u16 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut writer = CodeWriter::new(out);
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end_code + 1;
+    let mut code_width = min_code_size + 1;
+
+    writer.write(clear_code, code_width);
+    let mut iter = indices.iter();
+    let mut prefix = match iter.next() {
+        Some(&first) => first as u16,
+        None => {
+            writer.write(end_code, code_width);
+            writer.finish();
+            return;
+        }
+    };
+
+    for &index in iter {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, code_width);
+        if next_code <= MAX_LZW_CODE {
+            table.insert((prefix, index), next_code);
+            // Widen once the code just added needs another bit (the decoder adds it one code later)
+            if next_code == (1 << code_width) && code_width < 12 {
+                code_width += 1;
+            }
+            next_code += 1;
+        } else {
+            // Table full: start over
+            writer.write(clear_code, code_width);
+            table.clear();
+            next_code = end_code + 1;
+            code_width = min_code_size + 1;
+        }
+        prefix = index as u16;
+    }
+    writer.write(prefix, code_width);
+    writer.write(end_code, code_width);
+    writer.finish();
+}
+
+// Streams frames into a GIF89a file
+pub struct GifWriter {
+    out: Vec<u8>,
+    width: u16,
+    height: u16,
+    global_bits: Option<u8>,
+}
+
+impl GifWriter {
+    // Start a GIF; loop_count Some(0) loops forever, None plays once
+    pub fn new(width: u16, height: u16, global_palette: Option<&[[u8; 3]]>, loop_count: Option<u16>) -> Self {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"GIF89a");
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+
+        let global_bits = global_palette.map(|palette| palette_bits(palette.len()));
+        let packed = match global_bits {
+            // Global table present, 8-bit color resolution, table size
+            Some(bits) => 0x80 | 0x70 | (bits - 1),
+            None => 0x70,
+        };
+        out.extend_from_slice(&[packed, 0, 0]); // Background index 0, square pixels
+        if let (Some(palette), Some(bits)) = (global_palette, global_bits) {
+            write_palette(&mut out, palette, bits);
+        }
+
+        if let Some(count) = loop_count {
+            out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+            out.extend_from_slice(b"NETSCAPE2.0");
+            out.extend_from_slice(&[0x03, 0x01]);
+            out.extend_from_slice(&count.to_le_bytes());
+            out.push(0);
+        }
+
+        GifWriter { out, width, height, global_bits }
+    }
+
+    // Append a frame; it needs a local palette when the GIF has no global one
+    pub fn add_frame(&mut self, frame: &GifFrame) {
+        // Graphic control extension
+        let transparent_flag = frame.transparent_index.is_some() as u8;
+        self.out.extend_from_slice(&[0x21, 0xF9, 0x04, ((frame.disposal as u8) << 2) | transparent_flag]);
+        self.out.extend_from_slice(&frame.delay.to_le_bytes());
+        self.out.extend_from_slice(&[frame.transparent_index.unwrap_or(0), 0]);
+
+        // Image descriptor
+        self.out.push(0x2C);
+        for value in [frame.left, frame.top, frame.width, frame.height] {
+            self.out.extend_from_slice(&value.to_le_bytes());
+        }
+        let table_bits = match frame.local_palette {
+            Some(palette) => {
+                let bits = palette_bits(palette.len());
+                self.out.push(0x80 | (bits - 1));
+                write_palette(&mut self.out, palette, bits);
+                bits
+            }
+            None => {
+                self.out.push(0);
+                self.global_bits.unwrap_or(8)
+            }
+        };
+
+        let pixel_count = frame.width as usize * frame.height as usize;
+        lzw_encode(&frame.indices[..pixel_count], table_bits, &mut self.out);
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    // Close the file with the trailer
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.push(0x3B);
+        self.out
+    }
+}
+
+// Read `colors` RGB triplets from wasm memory
+fn palette_from_ptr(palette_ptr: *const u8, colors: usize) -> Vec<[u8; 3]> {
+    if palette_ptr.is_null() || colors == 0 { return Vec::new(); }
+    let bytes = unsafe { std::slice::from_raw_parts(palette_ptr, colors * 3) };
+    bytes.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect()
+}
+
+// GIF encoder for JS: create it, add frames, then call finish() for the file bytes
+#[wasm_bindgen]
+pub struct GifEncoder {
+    writer: Option<GifWriter>,
+    global_palette: Vec<[u8; 3]>,
+}
+
+#[wasm_bindgen]
+impl GifEncoder {
+    // palette_ptr holds `colors` RGB triplets for the global palette (null or 0 colors for none).
+    // loop_count: 0 loops forever, a negative value plays once.
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u16, height: u16, palette_ptr: *const u8, colors: usize, loop_count: i32) -> GifEncoder {
+        let global_palette = palette_from_ptr(palette_ptr, colors.min(256));
+        let loop_count = if loop_count < 0 { None } else { Some(loop_count.min(u16::MAX as i32) as u16) };
+        let palette = if global_palette.is_empty() { None } else { Some(global_palette.as_slice()) };
+        GifEncoder {
+            writer: Some(GifWriter::new(width, height, palette, loop_count)),
+            global_palette,
+        }
+    }
+
+    // Add a full-size frame of palette indices. delay is in hundredths of a second,
+    // transparent_index < 0 means no transparency, local_colors 0 uses the global palette.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn add_frame(
+        &mut self,
+        indices_ptr: *const u8,
+        delay: u16,
+        disposal: GifDisposal,
+        transparent_index: i32,
+        local_palette_ptr: *const u8,
+        local_colors: usize,
+    ) {
+        let Some(writer) = self.writer.as_mut() else { return };
+        let (width, height) = (writer.width(), writer.height());
+        let indices = unsafe { std::slice::from_raw_parts(indices_ptr, width as usize * height as usize) };
+        let local_palette = palette_from_ptr(local_palette_ptr, local_colors.min(256));
+        writer.add_frame(&GifFrame {
+            indices,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            delay,
+            disposal,
+            transparent_index: u8::try_from(transparent_index).ok(),
+            local_palette: if local_palette.is_empty() { None } else { Some(&local_palette) },
+        });
+    }
+
+    // Add a one-byte-per-pixel dithered frame (0 - 255 gray), mapping each value to
+    // the global palette entry with the closest luma
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    pub fn add_dithered_frame(&mut self, input_ptr: *const u8, delay: u16, disposal: GifDisposal) {
+        let Some(writer) = self.writer.as_mut() else { return };
+        let (width, height) = (writer.width(), writer.height());
+        let input = unsafe { std::slice::from_raw_parts(input_ptr, width as usize * height as usize) };
+
+        let lumas: Vec<f32> = self
+            .global_palette
+            .iter()
+            .map(|c| 0.299 * c[0] as f32 + 0.587 * c[1] as f32 + 0.114 * c[2] as f32)
+            .collect();
+        let mut lookup = [0u8; 256];
+        for (value, entry) in lookup.iter_mut().enumerate() {
+            let closest = lumas
+                .iter()
+                .enumerate()
+                .min_by(|a, b| (a.1 - value as f32).abs().total_cmp(&(b.1 - value as f32).abs()))
+                .map_or(0, |(i, _)| i);
+            *entry = closest as u8;
+        }
+        let indices: Vec<u8> = input.iter().map(|&v| lookup[v as usize]).collect();
+
+        writer.add_frame(&GifFrame {
+            indices: &indices,
+            left: 0,
+            top: 0,
+            width,
+            height,
+            delay,
+            disposal,
+            transparent_index: None,
+            local_palette: None,
+        });
+    }
+
+    // Finish the file and return its bytes; the encoder is empty afterwards
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.writer.take().map(GifWriter::finish).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Reference GIF LZW decoder: the indices, the number of clear codes after the
+    // first and the widest code read
+    fn lzw_decode(data: &[u8]) -> (Vec<u8>, usize, u8) {
+        let min_code_size = data[0];
+        let mut bytes = Vec::new();
+        let mut pos = 1;
+        loop {
+            let len = data[pos] as usize;
+            pos += 1;
+            if len == 0 { break; }
+            bytes.extend_from_slice(&data[pos..pos + len]);
+            pos += len;
+        }
+        assert_eq!(pos, data.len(), "data after the block terminator");
+
+        let clear_code = 1usize << min_code_size;
+        let end_code = clear_code + 1;
+        let reset = || (0..clear_code).map(|i| vec![i as u8]).chain([vec![], vec![]]).collect::<Vec<Vec<u8>>>();
+        let mut table = reset();
+        let mut width = min_code_size + 1;
+        let mut widest = width;
+        let mut previous: Option<usize> = None;
+        let mut clears = 0;
+        let mut output = Vec::new();
+        let mut bit = 0;
+        loop {
+            assert!(bit + width as usize <= bytes.len() * 8, "ran out of data before the end code");
+            let code = (0..width as usize).fold(0, |code, b| code | (((bytes[(bit + b) / 8] >> ((bit + b) % 8)) & 1) as usize) << b);
+            bit += width as usize;
+            if code == clear_code {
+                if bit > width as usize { clears += 1; }
+                table = reset();
+                width = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code { break; }
+            let entry = if code < table.len() {
+                table[code].clone()
+            } else {
+                // The one code the decoder cannot know yet: previous + its first index
+                assert_eq!(code, table.len(), "code beyond the table");
+                let mut entry = table[previous.expect("first code after a clear must be known")].clone();
+                entry.push(entry[0]);
+                entry
+            };
+            output.extend_from_slice(&entry);
+            if let Some(p) = previous {
+                if table.len() <= MAX_LZW_CODE as usize {
+                    let mut added = table[p].clone();
+                    added.push(entry[0]);
+                    table.push(added);
+                }
+            }
+            previous = Some(code);
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+                widest = widest.max(width);
+            }
+        }
+        (output, clears, widest)
+    }
+
+    fn round_trip(indices: &[u8], min_code_size: u8) -> (usize, u8) {
+        let mut data = Vec::new();
+        lzw_encode(indices, min_code_size, &mut data);
+        let (decoded, clears, widest) = lzw_decode(&data);
+        assert_eq!(decoded, indices);
+        (clears, widest)
+    }
+
+    #[test]
+    fn lzw_round_trips_short_inputs() {
+        round_trip(&[], 2);
+        round_trip(&[3], 2);
+        round_trip(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 2);
+        round_trip(&[1, 0, 1, 0, 1, 0, 1, 1, 1, 0, 0, 0], 1);
+        let ramp: Vec<u8> = (0..=255).collect();
+        round_trip(&ramp, 8);
+    }
+
+    #[test]
+    fn lzw_grows_to_twelve_bits_and_clears() {
+        // Random indices fill the 4096-entry table many times over
+        let mut rng = StdRng::seed_from_u64(9);
+        for min_code_size in [2u8, 4, 8] {
+            let colors = 1u16 << min_code_size;
+            let indices: Vec<u8> = (0..200_000).map(|_| rng.gen_range(0..colors) as u8).collect();
+            let (clears, widest) = round_trip(&indices, min_code_size);
+            assert_eq!(widest, 12);
+            assert!(clears > 0, "no clear code at min code size {}", min_code_size);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trips_long_runs() {
+        // Runs exercise the code-not-yet-in-table case (KwKwK)
+        let indices: Vec<u8> = (0..50_000).map(|i| ((i / 997) % 3) as u8).collect();
+        round_trip(&indices, 2);
+    }
+}
@@ -1,3 +1,170 @@
+use crate::vector::{VectorDot, VectorShape};
+
+// Halftone dithering with a rotated screen
+//
+// The screen is a square lattice of cells whose axes are rotated by the screen
+// angle. Each pixel is mapped into lattice coordinates to find the cell it
+// belongs to, the tone is sampled once at that cell's center, and the pixel is
+// set when it falls inside the dot grown for that tone. Because the lattice
+// itself is rotated (not just the offset inside an axis-aligned cell), the
+// angle changes the output the way it does on a real press.
+
+// A rotated halftone screen
+#[derive(Clone, Copy, Debug)]
+pub struct Screen {
+    pub cell_size: f32,        // Screen period in pixels (distance between dot centers)
+    pub max_dot_diameter: f32, // Diameter of the dot at full tone (255)
+    pub angle: f32,            // Screen angle in degrees
+    cos_angle: f32,
+    sin_angle: f32,
+}
+
+impl Screen {
+    pub fn new(cell_size: f32, max_dot_diameter: f32, angle: f32) -> Self {
+        let angle_rad = angle.to_radians();
+        Screen {
+            cell_size: cell_size.max(1.0),
+            max_dot_diameter: max_dot_diameter.max(0.0),
+            angle,
+            cos_angle: angle_rad.cos(),
+            sin_angle: angle_rad.sin(),
+        }
+    }
+
+    // Screen for a given ruling (lines per inch) on a device of `dpi` pixels per inch.
+    // Dots at full tone reach the cell corners so solid areas close up completely.
+    pub fn from_frequency(lpi: f32, dpi: f32, angle: f32) -> Self {
+        let cell_size = if lpi > 0.0 { dpi / lpi } else { 1.0 };
+        Screen::new(cell_size, cell_size * std::f32::consts::SQRT_2, angle)
+    }
+
+    // Convert image coordinates to (unscaled) lattice coordinates
+    fn lattice_coords(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.cos_angle + y * self.sin_angle,
+            -x * self.sin_angle + y * self.cos_angle,
+        )
+    }
+
+    // Convert lattice coordinates back to image coordinates
+    fn image_coords(&self, u: f32, v: f32) -> (f32, f32) {
+        (
+            u * self.cos_angle - v * self.sin_angle,
+            u * self.sin_angle + v * self.cos_angle,
+        )
+    }
+
+    // Integer lattice indices of the cell containing the image point (x, y)
+    pub fn cell_of(&self, x: f32, y: f32) -> (i64, i64) {
+        let (u, v) = self.lattice_coords(x, y);
+        (
+            (u / self.cell_size).floor() as i64,
+            (v / self.cell_size).floor() as i64,
+        )
+    }
+
+    // Image coordinates of the center of cell (i, j)
+    pub fn cell_center(&self, i: i64, j: i64) -> (f32, f32) {
+        let u = (i as f32 + 0.5) * self.cell_size;
+        let v = (j as f32 + 0.5) * self.cell_size;
+        self.image_coords(u, v)
+    }
+
+    // Dot radius for a tone value (0-255); bright tones grow larger (white) dots
+    pub fn dot_radius(&self, tone: u8) -> f32 {
+        (self.max_dot_diameter / 2.0) * (tone as f32 / 255.0)
+    }
+
+    // Range of lattice indices (inclusive) whose dots can reach a width x height image
+    pub fn cell_range(&self, width: usize, height: usize) -> ((i64, i64), (i64, i64)) {
+        let corners = [
+            (0.0, 0.0),
+            (width as f32, 0.0),
+            (0.0, height as f32),
+            (width as f32, height as f32),
+        ];
+        let mut i_range = (i64::MAX, i64::MIN);
+        let mut j_range = (i64::MAX, i64::MIN);
+        for &(x, y) in &corners {
+            let (i, j) = self.cell_of(x, y);
+            i_range = (i_range.0.min(i), i_range.1.max(i));
+            j_range = (j_range.0.min(j), j_range.1.max(j));
+        }
+        // One extra ring so cells whose centers sit just outside the image are kept
+        ((i_range.0 - 1, i_range.1 + 1), (j_range.0 - 1, j_range.1 + 1))
+    }
+}
+
+// Sample the tone under a cell center, clamping centers that fall off the image
+pub fn sample_tone(input: &[u8], width: usize, height: usize, cx: f32, cy: f32) -> u8 {
+    let sx = (cx.floor().max(0.0) as usize).min(width - 1);
+    let sy = (cy.floor().max(0.0) as usize).min(height - 1);
+    input[sy * width + sx]
+}
+
+// Render a screen into a one-byte-per-pixel output buffer (255 inside dots)
+pub fn screen_dither(input: &[u8], width: usize, height: usize, screen: &Screen, output: &mut [u8]) {
+    if width == 0 || height == 0 { return; }
+
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+
+            // Work with pixel centers so the lattice is symmetric around each dot
+            let px = x as f32 + 0.5;
+            let py = y as f32 + 0.5;
+
+            // Find the cell in rotated lattice space and its center in image space
+            let (i, j) = screen.cell_of(px, py);
+            let (cx, cy) = screen.cell_center(i, j);
+
+            // One tone per cell: every pixel of the dot uses the tone at its center
+            let tone = sample_tone(input, width, height, cx, cy);
+            let radius = screen.dot_radius(tone);
+
+            let dx = px - cx;
+            let dy = py - cy;
+            output[idx] = if dx * dx + dy * dy <= radius * radius { 255 } else { 0 };
+        }
+    }
+}
+
+// Exact dot geometry of a screen: the same dots screen_dither rasterizes
+pub fn halftone_dots(input: &[u8], width: usize, height: usize, screen: &Screen) -> Vec<VectorDot> {
+    let mut dots = Vec::new();
+    if width == 0 || height == 0 { return dots; }
+
+    let ((i_min, i_max), (j_min, j_max)) = screen.cell_range(width, height);
+    for j in j_min..=j_max {
+        for i in i_min..=i_max {
+            let (cx, cy) = screen.cell_center(i, j);
+            let radius = screen.dot_radius(sample_tone(input, width, height, cx, cy));
+            if radius <= 0.0 { continue; }
+
+            // Skip dots that cannot touch the image
+            if cx + radius < 0.0 || cy + radius < 0.0
+                || cx - radius > width as f32 || cy - radius > height as f32
+            {
+                continue;
+            }
+
+            // Pixels only ever belong to their own cell, so dots larger than the
+            // cell are cut off by its square, turning fully square at the corners
+            let half_cell = screen.cell_size / 2.0;
+            let (shape, rx, ry) = if radius <= half_cell {
+                (VectorShape::Circle, radius, radius)
+            } else if radius < half_cell * std::f32::consts::SQRT_2 {
+                (VectorShape::ClippedCircle, radius, half_cell)
+            } else {
+                (VectorShape::Square, half_cell, half_cell)
+            };
+
+            dots.push(VectorDot { shape, cx, cy, rx, ry, rotation: screen.angle });
+        }
+    }
+    dots
+}
+
 // Halftone dithering algorithm
 pub fn halftone_dither(
     input_ptr: *const u8,
@@ -11,45 +178,26 @@ pub fn halftone_dither(
     // Convert the input and output pointers to slices
     let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
     let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
-    
-    // Clear output buffer
-    for i in 0..output.len() {
-        output[i] = 0;
-    }
-    
-    // Calculate the rotation matrix for the specified angle
-    let angle_rad = angle * std::f32::consts::PI / 180.0;
-    let cos_angle = angle_rad.cos();
-    let sin_angle = angle_rad.sin();
-    
-    // Cell size (dot size + spacing)
-    let cell_size = dot_size + spacing;
-    
-    // Process the image in cell-sized blocks
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let pixel_value = input[idx];
-            
-            // Calculate the cell position for this pixel
-            let cell_x = (x / cell_size) * cell_size + cell_size / 2;
-            let cell_y = (y / cell_size) * cell_size + cell_size / 2;
-            
-            // Calculate rotated coordinates
-            let dx = (x as f32 - cell_x as f32);
-            let dy = (y as f32 - cell_y as f32);
-            let rotated_x = dx * cos_angle - dy * sin_angle;
-            let rotated_y = dx * sin_angle + dy * cos_angle;
-            
-            // Calculate the max dot radius based on the input value (0-255)
-            let max_radius = (dot_size as f32 / 2.0) * (pixel_value as f32 / 255.0);
-            
-            // Check if this pixel falls within the dot
-            let dist = (rotated_x * rotated_x + rotated_y * rotated_y).sqrt();
-            
-            if dist <= max_radius {
-                output[idx] = 255;
-            }
-        }
-    }
-} 
\ No newline at end of file
+
+    // Cell size (dot size + spacing) is the screen period
+    let screen = Screen::new((dot_size + spacing) as f32, dot_size as f32, angle);
+    screen_dither(input, width, height, &screen, output);
+}
+
+// Halftone dithering with a screen given by ruling and device resolution
+pub fn halftone_screen_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    dpi: f32,
+    angle: f32,
+    output_ptr: *mut u8,
+) {
+    // Convert the input and output pointers to slices
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
+
+    let screen = Screen::from_frequency(lpi, dpi, angle);
+    screen_dither(input, width, height, &screen, output);
+}
@@ -0,0 +1,206 @@
+use wasm_bindgen::prelude::*;
+use std::f32::consts::PI;
+
+// Human visual system models for halftone optimization
+//
+// Each model is a contrast sensitivity function over spatial frequency in
+// cycles per degree of visual angle. Viewing distance and device resolution
+// fix how many pixels one degree covers, so the same model gives a wider
+// filter for a fine print viewed up close than for a coarse screen. The
+// models are turned into a low-pass point spread function in pixels: the
+// Gaussian directly, the others by sampling the frequency response on a grid
+// and taking the inverse DFT. Filters are normalized to sum to 1, so flat
+// areas keep their tone.
+
+// Gaussian width in degrees: 1.5 pixels at 300 dpi viewed from 10 inches
+const GAUSSIAN_SIGMA_DEGREES: f32 = 0.0286;
+// Average luminance (cd/m^2) assumed by Näsänen's model
+const NASANEN_LUMINANCE: f32 = 11.0;
+// Frequency where the Mannos-Sakrison curve peaks; below it the response is held at 1
+const MANNOS_SAKRISON_PEAK: f32 = 8.0;
+// Largest filter radius in pixels, bounding the cost of each DBS update
+const MAX_RADIUS: usize = 16;
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HvsModel {
+    Gaussian = 0,       // Fixed-width Gaussian in visual angle
+    Nasanen = 1,        // Exponential falloff (Näsänen 1984)
+    // Mannos & Sakrison 1974 CSF, made low-pass. It keeps more fine detail than the
+    // others, so close up (e.g. 300 dpi at 10 inches) isolated dots in the lightest
+    // and darkest tones cost more than they gain and those tones come out flat.
+    MannosSakrison = 2,
+}
+
+// A model at a viewing distance (inches) and device resolution (dots per inch)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HvsFilter {
+    pub model: HvsModel,
+    pub viewing_distance: f32,
+    pub dpi: f32,
+}
+
+impl Default for HvsFilter {
+    fn default() -> Self {
+        HvsFilter { model: HvsModel::Gaussian, viewing_distance: 10.0, dpi: 300.0 }
+    }
+}
+
+// Pixels covered by one degree of visual angle
+pub fn pixels_per_degree(viewing_distance: f32, dpi: f32) -> f32 {
+    (viewing_distance * dpi * (PI / 180.0).tan()).max(1e-3)
+}
+
+// Relative sensitivity at a frequency in cycles per degree, 1 at DC
+pub fn contrast_sensitivity(model: HvsModel, frequency: f32) -> f32 {
+    match model {
+        HvsModel::Gaussian => {
+            let sigma = 1.0 / (2.0 * PI * GAUSSIAN_SIGMA_DEGREES);
+            (-frequency * frequency / (2.0 * sigma * sigma)).exp()
+        }
+        HvsModel::Nasanen => {
+            // a L^b exp(-f / (c ln L + d)) with the amplitude dropped
+            (-frequency / (0.525 * NASANEN_LUMINANCE.ln() + 3.91)).exp()
+        }
+        HvsModel::MannosSakrison => {
+            let mannos = |f: f32| 2.6 * (0.0192 + 0.114 * f) * (-(0.114 * f).powf(1.1)).exp();
+            if frequency <= MANNOS_SAKRISON_PEAK {
+                1.0
+            } else {
+                mannos(frequency) / mannos(MANNOS_SAKRISON_PEAK)
+            }
+        }
+    }
+}
+
+impl HvsFilter {
+    pub fn pixels_per_degree(&self) -> f32 {
+        pixels_per_degree(self.viewing_distance, self.dpi)
+    }
+
+    // Filter radius in pixels covering most of the point spread function
+    fn radius(&self) -> usize {
+        let ppd = self.pixels_per_degree();
+        let scale = match self.model {
+            HvsModel::Gaussian => 3.0 * GAUSSIAN_SIGMA_DEGREES * ppd,
+            // exp(-f / f0) has a slowly decaying r^-3 tail; 6 / (2 pi f0) holds almost all of it
+            HvsModel::Nasanen => 6.0 * ppd / (2.0 * PI * (0.525 * NASANEN_LUMINANCE.ln() + 3.91)),
+            HvsModel::MannosSakrison => 6.0 * ppd / (2.0 * PI * MANNOS_SAKRISON_PEAK),
+        };
+        (scale.ceil() as usize).clamp(1, MAX_RADIUS)
+    }
+
+    // Normalized point spread function, (2 * radius + 1)^2 weights, and its radius
+    pub fn kernel(&self) -> (Vec<f32>, usize) {
+        let radius = self.radius();
+        let diameter = 2 * radius + 1;
+        let mut psf = match self.model {
+            HvsModel::Gaussian => {
+                let sigma = GAUSSIAN_SIGMA_DEGREES * self.pixels_per_degree();
+                let mut psf = vec![0.0; diameter * diameter];
+                for y in 0..diameter {
+                    for x in 0..diameter {
+                        let dx = x as f32 - radius as f32;
+                        let dy = y as f32 - radius as f32;
+                        psf[y * diameter + x] = (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp();
+                    }
+                }
+                psf
+            }
+            _ => self.sampled_kernel(radius),
+        };
+        let sum: f32 = psf.iter().sum();
+        if sum.abs() > 1e-12 {
+            psf.iter_mut().for_each(|w| *w /= sum);
+        }
+        (psf, radius)
+    }
+
+    // Inverse DFT of the frequency response sampled on a grid four times the filter size.
+    // The response is real and even, so the transform is a separable sum of cosines.
+    fn sampled_kernel(&self, radius: usize) -> Vec<f32> {
+        let diameter = 2 * radius + 1;
+        let grid = (4 * diameter).next_power_of_two();
+        let ppd = self.pixels_per_degree();
+        let signed = |u: usize| if u <= grid / 2 { u as f32 } else { u as f32 - grid as f32 };
+
+        // cosines[u][x] = cos(2 pi u x / grid) for offsets x in -radius..=radius
+        let cosines: Vec<f32> = (0..grid)
+            .flat_map(|u| {
+                (0..diameter).map(move |x| (2.0 * PI * u as f32 * (x as f32 - radius as f32) / grid as f32).cos())
+            })
+            .collect();
+
+        // Sum over u for every (v, x), then over v for every (x, y)
+        let mut partial = vec![0.0f32; grid * diameter];
+        for v in 0..grid {
+            for u in 0..grid {
+                let frequency = (signed(u).powi(2) + signed(v).powi(2)).sqrt() / grid as f32 * ppd;
+                let response = contrast_sensitivity(self.model, frequency);
+                for x in 0..diameter {
+                    partial[v * diameter + x] += response * cosines[u * diameter + x];
+                }
+            }
+        }
+        let mut psf = vec![0.0f32; diameter * diameter];
+        for y in 0..diameter {
+            for x in 0..diameter {
+                psf[y * diameter + x] = (0..grid).map(|v| partial[v * diameter + x] * cosines[v * diameter + y]).sum();
+            }
+        }
+        psf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_MODELS: [HvsModel; 3] = [HvsModel::Gaussian, HvsModel::Nasanen, HvsModel::MannosSakrison];
+
+    #[test]
+    fn sensitivity_is_one_at_dc_and_falls_with_frequency() {
+        for model in ALL_MODELS {
+            assert!((contrast_sensitivity(model, 0.0) - 1.0).abs() < 1e-6, "{:?}", model);
+            let mut previous = 1.0;
+            for step in 1..=120 {
+                let sensitivity = contrast_sensitivity(model, step as f32 * 0.5);
+                assert!(sensitivity <= previous + 1e-6 && sensitivity > 0.0, "{:?} at {} cpd", model, step as f32 * 0.5);
+                previous = sensitivity;
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_are_normalized_and_symmetric() {
+        for model in ALL_MODELS {
+            for (viewing_distance, dpi) in [(10.0, 300.0), (20.0, 150.0), (4.0, 600.0), (30.0, 600.0)] {
+                let (psf, radius) = HvsFilter { model, viewing_distance, dpi }.kernel();
+                let diameter = 2 * radius + 1;
+                assert!(radius <= MAX_RADIUS);
+                assert_eq!(psf.len(), diameter * diameter);
+                assert!((psf.iter().sum::<f32>() - 1.0).abs() < 1e-4, "{:?} at {} in, {} dpi", model, viewing_distance, dpi);
+                for y in 0..diameter {
+                    for x in 0..diameter {
+                        let weight = psf[y * diameter + x];
+                        for mirrored in [psf[x * diameter + y], psf[y * diameter + diameter - 1 - x], psf[(diameter - 1 - y) * diameter + x]] {
+                            assert!((weight - mirrored).abs() < 1e-5, "{:?} at ({}, {})", model, x, y);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn viewing_from_farther_away_blurs_more_pixels() {
+        for model in ALL_MODELS {
+            let center = |viewing_distance| {
+                let (psf, radius) = HvsFilter { model, viewing_distance, dpi: 300.0 }.kernel();
+                psf[radius * (2 * radius + 1) + radius]
+            };
+            assert!(center(5.0) > center(10.0) && center(10.0) > center(20.0), "{:?}", model);
+        }
+    }
+}
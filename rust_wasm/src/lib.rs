@@ -5,6 +5,7 @@ mod floyd_steinberg;
 mod atkinson;
 mod halftone;
 mod edge_detection;
+mod line_art;
 mod random;
 mod sierra_lite;
 mod burkes;
@@ -12,11 +13,27 @@ mod stucki;
 mod jarvis_judice_ninke;
 mod pattern;
 mod riemersma;
+mod scan_path;
+mod error_diffusion;
 mod blue_noise;
 mod void_and_cluster;
 mod direct_binary_search;
+mod hvs;
 mod multi_tone;
 mod selective;
+mod segmentation;
+mod mask;
+mod supercell;
+mod cmyk;
+mod rgb_dither;
+mod fm_screen;
+mod vector;
+mod svg_trace;
+mod encode;
+mod animation;
+mod gif;
+#[cfg(feature = "native")]
+pub mod decode;
 
 // Memory allocation functions
 #[wasm_bindgen]
@@ -47,6 +64,47 @@ pub fn ordered_dither(
     ordered::ordered_dither(input_ptr, width, height, dot_size, output_ptr);
 }
 
+// Export the DotShape enum
+pub use supercell::DotShape;
+
+// Clustered-dot ordered dithering with a tileable supercell screen
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn clustered_dot_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    angle: f32,
+    dpi: f32,
+    shape: DotShape,
+    output_ptr: *mut u8,
+) {
+    ordered::clustered_dot_dither(input_ptr, width, height, lpi, angle, dpi, shape, output_ptr);
+}
+
+// Side length of the supercell threshold array for a screen
+#[wasm_bindgen]
+pub fn screen_threshold_array_size(lpi: f32, angle: f32, dpi: f32) -> usize {
+    supercell::threshold_array_size(lpi, angle, dpi)
+}
+
+// Write the supercell threshold array (size x size bytes) and return its size
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn generate_screen_threshold_array(
+    lpi: f32,
+    angle: f32,
+    dpi: f32,
+    shape: DotShape,
+    output_ptr: *mut u8,
+) -> usize {
+    let thresholds = supercell::generate_threshold_array(lpi, angle, dpi, shape);
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, thresholds.values.len()) };
+    output.copy_from_slice(&thresholds.values);
+    thresholds.size
+}
+
 #[wasm_bindgen]
 pub fn floyd_steinberg_dither(
     input_ptr: *const u8,
@@ -82,6 +140,20 @@ pub fn halftone_dither(
     halftone::halftone_dither(input_ptr, width, height, dot_size, spacing, angle, output_ptr);
 }
 
+// Halftone with a rotated screen given by ruling (lpi) and device resolution (dpi)
+#[wasm_bindgen]
+pub fn halftone_screen_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    dpi: f32,
+    angle: f32,
+    output_ptr: *mut u8,
+) {
+    halftone::halftone_screen_dither(input_ptr, width, height, lpi, dpi, angle, output_ptr);
+}
+
 #[wasm_bindgen]
 pub fn sobel_edge_detection(
     input_ptr: *const u8,
@@ -93,6 +165,28 @@ pub fn sobel_edge_detection(
     edge_detection::sobel_edge_detection(input_ptr, width, height, threshold, output_ptr);
 }
 
+pub use edge_detection::EdgeDetector;
+
+// Export edge detection with a choice of detector (one byte per pixel); gradient
+// magnitude and orientation (f32 per pixel, radians) are written unless their pointers are null
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn detect_edges(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    detector: EdgeDetector,
+    threshold: f32,
+    low_threshold: f32,
+    sigma: f32,
+    output_ptr: *mut u8,
+    magnitude_ptr: *mut f32,
+    orientation_ptr: *mut f32,
+) {
+    let options = edge_detection::EdgeOptions { detector, threshold, low_threshold, sigma };
+    edge_detection::detect_edges(input_ptr, width, height, &options, output_ptr, magnitude_ptr, orientation_ptr);
+}
+
 #[wasm_bindgen]
 pub fn random_dither(
     input_ptr: *const u8,
@@ -181,6 +275,21 @@ pub fn riemersma_dither(
     riemersma::riemersma_dither(input_ptr, width, height, threshold, output_ptr);
 }
 
+// Export Riemersma dithering with a configurable error queue (length, and the oldest
+// error's weight relative to the newest); one byte per pixel
+#[wasm_bindgen]
+pub fn riemersma_queue_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    threshold: u8,
+    queue_length: usize,
+    decay_ratio: f32,
+    output_ptr: *mut u8,
+) {
+    riemersma::riemersma_queue_dither(input_ptr, width, height, threshold, queue_length, decay_ratio, output_ptr);
+}
+
 // Export the new Blue Noise dither function
 #[wasm_bindgen]
 pub fn blue_noise_dither(
@@ -193,6 +302,31 @@ pub fn blue_noise_dither(
     blue_noise::blue_noise_dither(input_ptr, width, height, threshold, output_ptr);
 }
 
+// Blue-noise dithering with a void-and-cluster mask of the given size and seed (one byte per pixel)
+#[wasm_bindgen]
+pub fn blue_noise_mask_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    mask_size: usize,
+    seed: u32,
+    threshold: u8,
+    output_ptr: *mut u8,
+) {
+    blue_noise::blue_noise_mask_dither(input_ptr, width, height, mask_size, seed as u64, threshold, output_ptr);
+}
+
+// Write a size x size blue-noise threshold mask (0 - 255, uniform histogram)
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn generate_blue_noise_mask(size: usize, seed: u32, output_ptr: *mut u8) {
+    let mask = blue_noise::blue_noise_mask(size, seed as u64);
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, mask.len()) };
+    for (out, &value) in output.iter_mut().zip(mask.iter()) {
+        *out = (value * 256.0) as u8;
+    }
+}
+
 // Export the new Void and Cluster dither function
 #[wasm_bindgen]
 pub fn void_and_cluster_dither(
@@ -217,6 +351,74 @@ pub fn direct_binary_search_dither(
     direct_binary_search::direct_binary_search_dither(input_ptr, width, height, threshold, output_ptr);
 }
 
+// Export the HvsModel enum
+pub use hvs::HvsModel;
+
+// Export Direct Binary Search with configurable iterations, convergence, moves and eye model
+// (viewing distance in inches, resolution in dpi); one byte per pixel
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn dbs_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    threshold: u8,
+    max_iterations: usize,
+    min_change_fraction: f32,
+    swap_radius: usize,
+    hvs_model: HvsModel,
+    viewing_distance: f32,
+    dpi: f32,
+    output_ptr: *mut u8,
+) {
+    let hvs = hvs::HvsFilter { model: hvs_model, viewing_distance, dpi };
+    let options = direct_binary_search::DbsOptions { max_iterations, min_change_fraction, swap_radius, hvs };
+    direct_binary_search::dbs_dither(input_ptr, width, height, threshold, &options, output_ptr);
+}
+
+// Export multi-level gray Direct Binary Search (`levels` evenly spaced tones, one byte per pixel)
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn dbs_levels_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    levels: usize,
+    max_iterations: usize,
+    min_change_fraction: f32,
+    swap_radius: usize,
+    hvs_model: HvsModel,
+    viewing_distance: f32,
+    dpi: f32,
+    output_ptr: *mut u8,
+) {
+    let hvs = hvs::HvsFilter { model: hvs_model, viewing_distance, dpi };
+    let options = direct_binary_search::DbsOptions { max_iterations, min_change_fraction, swap_radius, hvs };
+    direct_binary_search::dbs_levels_dither(input_ptr, width, height, levels, &options, output_ptr);
+}
+
+// Export palette color Direct Binary Search (RGBA input, `colors` RGB triplets at palette_ptr, RGBA output)
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn dbs_palette_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    palette_ptr: *const u8,
+    colors: usize,
+    max_iterations: usize,
+    min_change_fraction: f32,
+    swap_radius: usize,
+    hvs_model: HvsModel,
+    viewing_distance: f32,
+    dpi: f32,
+    output_ptr: *mut u8,
+) {
+    let hvs = hvs::HvsFilter { model: hvs_model, viewing_distance, dpi };
+    let options = direct_binary_search::DbsOptions { max_iterations, min_change_fraction, swap_radius, hvs };
+    direct_binary_search::dbs_palette_dither(input_ptr, width, height, palette_ptr, colors, &options, output_ptr);
+}
+
 // Export the MultiToneAlgorithm enum
 pub use multi_tone::MultiToneAlgorithm;
 
@@ -281,4 +483,431 @@ pub fn selective_dither(
         default_mt_levels, default_mt_algo,
         output_ptr,
     );
-} 
\ No newline at end of file
+}
+
+// Export the CMYK separation enums
+pub use cmyk::{CmykOutput, SeparationMode};
+
+// CMYK separation with a halftone screen per plate
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn cmyk_halftone(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    dpi: f32,
+    // Per-plate screens (C, M, Y, K); pass null for the defaults
+    angles: *const f32,
+    shapes: *const u32,
+    // Separation settings
+    mode: SeparationMode,
+    black_generation: f32,
+    black_start: f32,
+    under_color_removal: f32,
+    ink_limit: f32,
+    // Output: four planes (4 * width * height bytes) or an RGBA preview
+    output: CmykOutput,
+    output_ptr: *mut u8,
+) {
+    let options = cmyk::SeparationOptions {
+        mode,
+        black_generation,
+        black_start,
+        under_color_removal,
+        ink_limit,
+    };
+    cmyk::cmyk_halftone(rgba_ptr, width, height, lpi, dpi, angles, shapes, options, output, output_ptr);
+}
+
+// Per-channel RGB dithering with any algorithm and `levels` values per channel;
+// the algorithm parameters are the same as selective_dither's defaults
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn rgb_dither(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    algorithm: DitheringAlgorithmType,
+    levels: usize,
+    threshold: u8,
+    dot_size: usize,
+    spacing: usize,
+    angle: f32,
+    noise_amount: f32,
+    pattern_type: PatternType,
+    pattern_size: usize,
+    multi_tone_levels: usize,
+    multi_tone_algo: MultiToneAlgorithm,
+    decorrelate: bool, // Offset/rotate mask-based algorithms between channels
+    output_ptr: *mut u8, // RGBA output
+) {
+    let params = selective::AlgorithmParams {
+        threshold,
+        dot_size,
+        spacing,
+        angle,
+        noise_amount,
+        pattern_type,
+        pattern_size,
+        multi_tone_levels,
+        multi_tone_algo,
+    };
+    rgb_dither::rgb_dither(rgba_ptr, width, height, algorithm, &params, levels, decorrelate, output_ptr);
+}
+
+// Export the FmMaskSource enum
+pub use fm_screen::FmMaskSource;
+
+// Stochastic (FM) screening with a fixed minimum dot size in device pixels
+#[wasm_bindgen]
+pub fn fm_screen_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    dot_size: usize,
+    mask_source: FmMaskSource,
+    output_ptr: *mut u8,
+) {
+    fm_screen::fm_screen_dither(input_ptr, width, height, dot_size, mask_source, output_ptr);
+}
+
+// Export the vector geometry enums
+pub use vector::{SvgUnit, VectorShape};
+
+// Halftone dots as [shape, cx, cy, rx, ry, rotation] per dot (see vector::VectorShape)
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn halftone_dot_geometry(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    dot_size: usize,
+    spacing: usize,
+    angle: f32,
+) -> Vec<f32> {
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let screen = halftone::Screen::new((dot_size + spacing) as f32, dot_size as f32, angle);
+    vector::flatten_dots(&halftone::halftone_dots(input, width, height, &screen))
+}
+
+// Halftone dots serialized straight to SVG
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_many_arguments)]
+pub fn halftone_svg(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    dot_size: usize,
+    spacing: usize,
+    angle: f32,
+    unit: SvgUnit,
+    dpi: f32,
+    include_background: bool,
+) -> String {
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let screen = halftone::Screen::new((dot_size + spacing) as f32, dot_size as f32, angle);
+    let dots = halftone::halftone_dots(input, width, height, &screen);
+    let options = vector::SvgOptions::matching_raster(unit, dpi, include_background);
+    vector::dots_to_svg(&dots, width, height, &options)
+}
+
+// Pattern shapes as [shape, cx, cy, rx, ry, rotation] per shape
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn pattern_dot_geometry(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    pattern_type: PatternType,
+    pattern_size: usize,
+) -> Vec<f32> {
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    vector::flatten_dots(&pattern::pattern_dots(input, width, height, pattern_type, pattern_size))
+}
+
+// Pattern shapes serialized straight to SVG
+#[wasm_bindgen]
+#[allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_many_arguments)]
+pub fn pattern_svg(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    pattern_type: PatternType,
+    pattern_size: usize,
+    unit: SvgUnit,
+    dpi: f32,
+    include_background: bool,
+) -> String {
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let dots = pattern::pattern_dots(input, width, height, pattern_type, pattern_size);
+    let options = vector::SvgOptions::matching_raster(unit, dpi, include_background);
+    vector::dots_to_svg(&dots, width, height, &options)
+}
+
+pub use svg_trace::{SvgTrace, SvgTraceMode};
+
+// Trace a one-byte-per-pixel dithered image into compact SVG (dark pixels drawn)
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn binary_to_svg(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    mode: SvgTraceMode,
+    despeckle_area: usize,
+    unit: SvgUnit,
+    dpi: f32,
+    include_background: bool,
+) -> SvgTrace {
+    svg_trace::binary_to_svg(input_ptr, width, height, mode, despeckle_area, unit, dpi, include_background)
+}
+
+pub use encode::{NetpbmFormat, PngColorMode};
+
+// Encode dithered pixels (channels = 1 for gray, 3 for RGB, 4 for RGBA) as a bit-depth-correct PNG
+#[wasm_bindgen]
+pub fn encode_png(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    channels: usize,
+    mode: PngColorMode,
+) -> Vec<u8> {
+    encode::encode_png_pixels(input_ptr, width, height, channels, mode)
+}
+
+// Encode dithered pixels as PBM, PGM or PPM
+#[wasm_bindgen]
+pub fn encode_netpbm(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    channels: usize,
+    format: NetpbmFormat,
+) -> Vec<u8> {
+    encode::encode_netpbm_pixels(input_ptr, width, height, channels, format)
+}
+
+// Encode dithered pixels as a 1, 4 or 8 bpp BMP
+#[wasm_bindgen]
+pub fn encode_bmp(input_ptr: *const u8, width: usize, height: usize, channels: usize) -> Vec<u8> {
+    encode::encode_bmp_pixels(input_ptr, width, height, channels)
+}
+
+// Export the animation ditherer with its algorithm and temporal settings for native callers
+pub use animation::{AnimationDitherer, TemporalOptions};
+pub use selective::AlgorithmParams;
+
+// Dither a sequence of frames (frame_count * width * height bytes) with temporal
+// coherence; returns the mean fraction of pixels that flip between frames
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn dither_animation(
+    frames_ptr: *const u8,
+    frame_count: usize,
+    width: usize,
+    height: usize,
+    algorithm: DitheringAlgorithmType,
+    threshold: u8,
+    dot_size: usize,
+    noise_amount: f32,
+    seed: u32,
+    reuse_unchanged: bool,
+    change_tolerance: u8,
+    hysteresis: f32,
+    output_ptr: *mut u8,
+) -> f32 {
+    let params = selective::AlgorithmParams { threshold, dot_size, noise_amount, ..Default::default() };
+    let options = animation::TemporalOptions { seed: seed as u64, reuse_unchanged, change_tolerance, hysteresis };
+    animation::dither_animation(frames_ptr, frame_count, width, height, algorithm, params, options, output_ptr)
+}
+
+// Export the GIF encoder and its frame disposal modes
+pub use gif::{GifDisposal, GifEncoder};
+
+// Export the ScanPath and DiffusionKernel enums
+pub use scan_path::ScanPath;
+pub use error_diffusion::DiffusionKernel;
+
+// Export error diffusion with any kernel along any scan path (one byte per pixel)
+#[wasm_bindgen]
+pub fn path_diffusion_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    output_ptr: *mut u8,
+) {
+    error_diffusion::path_diffusion_dither(input_ptr, width, height, kernel, path, threshold, output_ptr);
+}
+
+// Export edge-aware error diffusion: error is kept from crossing gradient edges
+// above edge_threshold (scaled by damping 0-1), with optional edge sharpening first
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn edge_aware_diffusion_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    kernel: DiffusionKernel,
+    path: ScanPath,
+    threshold: u8,
+    detector: EdgeDetector,
+    sigma: f32,
+    edge_threshold: f32,
+    damping: f32,
+    sharpen: f32,
+    output_ptr: *mut u8,
+) {
+    let options = error_diffusion::EdgeAwareOptions { detector, sigma, edge_threshold, damping, sharpen };
+    error_diffusion::edge_aware_diffusion_dither(input_ptr, width, height, kernel, path, threshold, &options, output_ptr);
+}
+
+// Export line art: edges from any detector as black strokes `thickness` pixels
+// wide over a fill dithered with any selectable algorithm (one byte per pixel)
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn line_art_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    fill_algorithm: DitheringAlgorithmType,
+    threshold: u8,
+    dot_size: usize,
+    noise_amount: f32,
+    detector: EdgeDetector,
+    edge_threshold: f32,
+    low_threshold: f32,
+    sigma: f32,
+    thickness: f32,
+    output_ptr: *mut u8,
+) {
+    let params = selective::AlgorithmParams { threshold, dot_size, noise_amount, ..Default::default() };
+    let edges = edge_detection::EdgeOptions { detector, threshold: edge_threshold, low_threshold, sigma };
+    let options = line_art::LineArtOptions { edges, thickness };
+    line_art::line_art_dither(input_ptr, width, height, fill_algorithm, &params, &options, output_ptr);
+}
+
+pub use segmentation::SegmentationMethod;
+
+// Export automatic segmentation of an RGBA image: writes one u32 region label per
+// pixel and returns the region count. `regions` is the SLIC superpixel count or
+// the k-means cluster count; edge regions use the edge detector settings.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn segment_image(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    method: SegmentationMethod,
+    regions: usize,
+    compactness: f32,
+    min_region_size: usize,
+    detector: EdgeDetector,
+    edge_threshold: f32,
+    low_threshold: f32,
+    sigma: f32,
+    seed: u32,
+    labels_ptr: *mut u32,
+) -> usize {
+    let edges = edge_detection::EdgeOptions { detector, threshold: edge_threshold, low_threshold, sigma };
+    let options = segmentation::SegmentationOptions {
+        method, regions, compactness, edges, min_region_size, seed: seed as u64, ..Default::default()
+    };
+    segmentation::segment_image(rgba_ptr, width, height, &options, labels_ptr)
+}
+
+// Mask of one labeled region (255 inside, 0 outside) to pass to selective_dither
+#[wasm_bindgen]
+pub fn region_mask(labels_ptr: *const u32, width: usize, height: usize, label: u32, mask_ptr: *mut u8) {
+    segmentation::region_mask(labels_ptr, width, height, label, mask_ptr);
+}
+
+// Bounding boxes of labeled regions, four u32 (x1, y1, x2, y2, inclusive) per region
+#[wasm_bindgen]
+pub fn region_bounds(labels_ptr: *const u32, width: usize, height: usize, count: usize, bounds_ptr: *mut u32) {
+    segmentation::region_bounds(labels_ptr, width, height, count, bounds_ptr);
+}
+
+// Export mask construction: shapes drawn straight into a width * height buffer
+// (255 inside, 0 outside) with anti-aliased edges, an optional feather radius in
+// pixels and inversion. Angles are clockwise degrees about the shape's center.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn ellipse_mask(
+    width: usize,
+    height: usize,
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+    angle: f32,
+    feather: f32,
+    invert: bool,
+    output_ptr: *mut u8,
+) {
+    let shape = mask::Shape::Ellipse { cx, cy, rx, ry, angle };
+    mask::shape_mask_into(width, height, &shape, &mask::MaskStyle { feather, invert }, output_ptr);
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn rounded_rect_mask(
+    width: usize,
+    height: usize,
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    corner_radius: f32,
+    angle: f32,
+    feather: f32,
+    invert: bool,
+    output_ptr: *mut u8,
+) {
+    let shape = mask::Shape::RoundedRect { left, top, right, bottom, corner_radius, angle };
+    mask::shape_mask_into(width, height, &shape, &mask::MaskStyle { feather, invert }, output_ptr);
+}
+
+// Polygon from `vertex_count` (x, y) f32 pairs at vertices_ptr, filled even-odd
+#[wasm_bindgen]
+pub fn polygon_mask(
+    width: usize,
+    height: usize,
+    vertices_ptr: *const f32,
+    vertex_count: usize,
+    feather: f32,
+    invert: bool,
+    output_ptr: *mut u8,
+) {
+    let shape = mask::polygon_from_ptr(vertices_ptr, vertex_count);
+    mask::shape_mask_into(width, height, &shape, &mask::MaskStyle { feather, invert }, output_ptr);
+}
+
+pub use mask::{MaskOp, Morphology};
+
+// Export mask refinement, each working in place on a width * height mask:
+// set operations (the second mask is combined into the first), inversion,
+// disk morphology and distance-transform feathering, all radii in pixels
+#[wasm_bindgen]
+pub fn combine_masks(target_ptr: *mut u8, other_ptr: *const u8, width: usize, height: usize, op: MaskOp) {
+    mask::combine_masks(target_ptr, other_ptr, width, height, op);
+}
+
+#[wasm_bindgen]
+pub fn invert_mask(mask_ptr: *mut u8, width: usize, height: usize) {
+    mask::invert_mask(mask_ptr, width, height);
+}
+
+#[wasm_bindgen]
+pub fn morphology_mask(mask_ptr: *mut u8, width: usize, height: usize, op: Morphology, radius: f32) {
+    mask::morphology_mask(mask_ptr, width, height, op, radius);
+}
+
+#[wasm_bindgen]
+pub fn feather_mask(mask_ptr: *mut u8, width: usize, height: usize, radius: f32) {
+    mask::feather_mask(mask_ptr, width, height, radius);
+}
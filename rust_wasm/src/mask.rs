@@ -0,0 +1,541 @@
+use wasm_bindgen::prelude::*;
+use std::collections::VecDeque;
+
+// Mask construction for selective dithering
+//
+// Masks are one byte per pixel, 255 inside a shape and 0 outside. Each shape
+// is described by its signed distance (positive inside) from the center of
+// every pixel, and coverage ramps linearly across the edge: over one pixel
+// without feathering, which anti-aliases the outline, or over twice the
+// feather radius, half inside and half outside. Ellipses and rounded
+// rectangles may be rotated about their centers; angles are in degrees,
+// clockwise on screen. Polygons are filled with the even-odd rule.
+// Shapes are drawn straight into a caller's buffer, so the wasm exports fill
+// memory JS allocated with `allocate` without another copy.
+//
+// Masks can then be refined in place. Set operations treat mask values as
+// degrees of membership (union is the maximum, intersection the minimum), so
+// they are exact on hard masks and keep soft edges soft. Dilation and erosion
+// take the maximum or minimum over a disk, with pixels outside the image
+// ignored so masks touching the border are not eaten away. Feathering thresholds
+// the mask at half coverage and ramps it by the exact Euclidean distance to
+// that outline, so any region, not only a drawn shape, gets an even soft edge.
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaskOp {
+    Union = 0,        // In either mask
+    Intersection = 1, // In both masks
+    Subtract = 2,     // In the first mask but not the second
+    Xor = 3,          // In exactly one mask
+}
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Morphology {
+    Dilate = 0, // Grow by the radius
+    Erode = 1,  // Shrink by the radius
+    Open = 2,   // Erode then dilate: removes specks and thin spurs
+    Close = 3,  // Dilate then erode: fills pinholes and narrow gaps
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Ellipse { cx: f32, cy: f32, rx: f32, ry: f32, angle: f32 },
+    RoundedRect { left: f32, top: f32, right: f32, bottom: f32, corner_radius: f32, angle: f32 },
+    Polygon(Vec<[f32; 2]>), // Vertices, closed automatically
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaskStyle {
+    pub feather: f32, // Soft edge radius in pixels; 0 for a one-pixel anti-aliased edge
+    pub invert: bool, // Cover everything outside the shape instead
+}
+
+impl Default for MaskStyle {
+    fn default() -> Self {
+        MaskStyle { feather: 0.0, invert: false }
+    }
+}
+
+impl MaskStyle {
+    // Half the width of the coverage ramp across the edge
+    fn half_ramp(&self) -> f32 {
+        self.feather.max(0.5)
+    }
+
+    // Mask value for a signed distance from the edge (positive inside)
+    fn coverage(&self, distance: f32) -> u8 {
+        let value = (0.5 + distance / (2.0 * self.half_ramp())).clamp(0.0, 1.0);
+        let value = if self.invert { 1.0 - value } else { value };
+        (value * 255.0).round() as u8
+    }
+}
+
+// Fill a mask from a signed distance function of pixel-center coordinates
+fn fill_distance(mask: &mut [u8], width: usize, style: &MaskStyle, distance: impl Fn(f32, f32) -> f32) {
+    for (i, m) in mask.iter_mut().enumerate() {
+        *m = style.coverage(distance((i % width) as f32 + 0.5, (i / width) as f32 + 0.5));
+    }
+}
+
+// Rotate (x, y) about (cx, cy) into a shape's own frame, undoing a clockwise rotation by `angle` degrees
+fn to_shape_frame(x: f32, y: f32, cx: f32, cy: f32, angle: f32) -> (f32, f32) {
+    let (sin, cos) = (-angle.to_radians()).sin_cos();
+    let (dx, dy) = (x - cx, y - cy);
+    (dx * cos - dy * sin, dx * sin + dy * cos)
+}
+
+fn draw_polygon(mask: &mut [u8], width: usize, height: usize, vertices: &[[f32; 2]], style: &MaskStyle) {
+    if vertices.len() < 3 {
+        mask.fill(style.coverage(f32::NEG_INFINITY));
+        return;
+    }
+    let edges: Vec<([f32; 2], [f32; 2])> =
+        (0..vertices.len()).map(|i| (vertices[i], vertices[(i + 1) % vertices.len()])).collect();
+
+    // Inside or outside: crossings of each row through the pixel centers
+    let mut inside = vec![false; width * height];
+    let mut crossings: Vec<f32> = Vec::new();
+    for y in 0..height {
+        let sy = y as f32 + 0.5;
+        crossings.clear();
+        for &(a, b) in &edges {
+            if (a[1] <= sy) != (b[1] <= sy) {
+                crossings.push(a[0] + (sy - a[1]) / (b[1] - a[1]) * (b[0] - a[0]));
+            }
+        }
+        crossings.sort_by(f32::total_cmp);
+        for span in crossings.chunks_exact(2) {
+            // Pixels whose centers lie between the two crossings
+            let start = (span[0] - 0.5).ceil().clamp(0.0, width as f32) as usize;
+            let end = (span[1] - 0.5).ceil().clamp(0.0, width as f32) as usize;
+            inside[y * width + start..y * width + end].fill(true);
+        }
+    }
+
+    // Distance to the outline, only needed within the ramp around each edge
+    let band = style.half_ramp();
+    let mut distance = vec![band; width * height];
+    // Pixels whose centers lie within the band of the span lo..hi
+    let pixels = |lo: f32, hi: f32, limit: usize| {
+        let start = (lo - band - 0.5).floor().clamp(0.0, limit as f32) as usize;
+        let end = ((hi + band - 0.5).floor() + 1.0).clamp(0.0, limit as f32) as usize;
+        start..end
+    };
+    for &(a, b) in &edges {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length_sq = dx * dx + dy * dy;
+        for y in pixels(a[1].min(b[1]), a[1].max(b[1]), height) {
+            for x in pixels(a[0].min(b[0]), a[0].max(b[0]), width) {
+                let (px, py) = (x as f32 + 0.5 - a[0], y as f32 + 0.5 - a[1]);
+                let t = if length_sq > 0.0 { ((px * dx + py * dy) / length_sq).clamp(0.0, 1.0) } else { 0.0 };
+                let d = ((px - t * dx).powi(2) + (py - t * dy).powi(2)).sqrt();
+                let i = y * width + x;
+                distance[i] = distance[i].min(d);
+            }
+        }
+    }
+
+    for ((m, &inside), &d) in mask.iter_mut().zip(&inside).zip(&distance) {
+        *m = style.coverage(if inside { d } else { -d });
+    }
+}
+
+// Draw a shape into a width * height mask, replacing its contents
+pub fn draw_shape(mask: &mut [u8], width: usize, height: usize, shape: &Shape, style: &MaskStyle) {
+    if width == 0 || height == 0 { return; }
+    match *shape {
+        Shape::Ellipse { cx, cy, rx, ry, angle } => {
+            if rx <= 0.0 || ry <= 0.0 {
+                mask.fill(style.coverage(f32::NEG_INFINITY));
+                return;
+            }
+            fill_distance(mask, width, style, |x, y| {
+                let (px, py) = to_shape_frame(x, y, cx, cy, angle);
+                // Distance approximation from the normalized radius and its gradient (exact for circles)
+                let k0 = ((px / rx).powi(2) + (py / ry).powi(2)).sqrt();
+                let k1 = ((px / (rx * rx)).powi(2) + (py / (ry * ry)).powi(2)).sqrt();
+                if k1 == 0.0 { rx.min(ry) } else { -k0 * (k0 - 1.0) / k1 }
+            });
+        }
+        Shape::RoundedRect { left, top, right, bottom, corner_radius, angle } => {
+            let (half_width, half_height) = ((right - left).abs() / 2.0, (bottom - top).abs() / 2.0);
+            let (cx, cy) = ((left + right) / 2.0, (top + bottom) / 2.0);
+            let radius = corner_radius.clamp(0.0, half_width.min(half_height));
+            fill_distance(mask, width, style, |x, y| {
+                let (px, py) = to_shape_frame(x, y, cx, cy, angle);
+                // Distance to the inner rectangle the corner circles are centered on, less their radius
+                let qx = px.abs() - (half_width - radius);
+                let qy = py.abs() - (half_height - radius);
+                let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+                let inside = qx.max(qy).min(0.0);
+                radius - outside - inside
+            });
+        }
+        Shape::Polygon(ref vertices) => draw_polygon(mask, width, height, vertices, style),
+    }
+}
+
+// Draw a shape into the width * height bytes at output_ptr
+pub fn shape_mask_into(width: usize, height: usize, shape: &Shape, style: &MaskStyle, output_ptr: *mut u8) {
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
+    draw_shape(output, width, height, shape, style);
+}
+
+// Polygon vertices from `vertex_count` (x, y) f32 pairs
+pub fn polygon_from_ptr(vertices_ptr: *const f32, vertex_count: usize) -> Shape {
+    if vertices_ptr.is_null() { return Shape::Polygon(Vec::new()); }
+    let coordinates = unsafe { std::slice::from_raw_parts(vertices_ptr, vertex_count * 2) };
+    Shape::Polygon(coordinates.chunks_exact(2).map(|p| [p[0], p[1]]).collect())
+}
+
+// Combine `other` into `target` pixel by pixel
+pub fn combine(target: &mut [u8], other: &[u8], op: MaskOp) {
+    for (t, &o) in target.iter_mut().zip(other) {
+        *t = match op {
+            MaskOp::Union => (*t).max(o),
+            MaskOp::Intersection => (*t).min(o),
+            MaskOp::Subtract => (*t).min(255 - o),
+            MaskOp::Xor => (*t).min(255 - o).max((255 - *t).min(o)),
+        };
+    }
+}
+
+pub fn invert(mask: &mut [u8]) {
+    mask.iter_mut().for_each(|m| *m = 255 - *m);
+}
+
+// Maximum (dilate) or minimum (erode) over a disk of the given radius
+fn disk_filter(mask: &[u8], width: usize, height: usize, radius: f32, dilate: bool) -> Vec<u8> {
+    let reach = radius.max(0.0).floor() as isize;
+    let better = |a: u8, b: u8| if dilate { a > b } else { a < b };
+    let mut result = vec![if dilate { 0u8 } else { 255u8 }; mask.len()];
+    let mut window: VecDeque<usize> = VecDeque::with_capacity(width);
+    // The disk is a stack of rows; each contributes a sliding extreme over its half width
+    for dy in -reach..=reach {
+        let half = (radius * radius - (dy * dy) as f32).max(0.0).sqrt().floor() as usize;
+        for y in 0..height {
+            let sy = y as isize + dy;
+            if sy < 0 || sy >= height as isize { continue; }
+            let row = &mask[sy as usize * width..(sy as usize + 1) * width];
+            let out = &mut result[y * width..(y + 1) * width];
+            // Monotonic queue of candidates for the extreme of row[x - half..=x + half]
+            window.clear();
+            let mut next = 0;
+            for (x, target) in out.iter_mut().enumerate() {
+                while next < width && next <= x + half {
+                    while window.back().is_some_and(|&b| !better(row[b], row[next])) {
+                        window.pop_back();
+                    }
+                    window.push_back(next);
+                    next += 1;
+                }
+                while window.front().is_some_and(|&f| f + half < x) {
+                    window.pop_front();
+                }
+                let extreme = row[window[0]];
+                if better(extreme, *target) {
+                    *target = extreme;
+                }
+            }
+        }
+    }
+    result
+}
+
+// Dilate, erode, open or close with a disk of the given radius in pixels
+pub fn morphology(mask: &[u8], width: usize, height: usize, op: Morphology, radius: f32) -> Vec<u8> {
+    if radius < 1.0 { return mask.to_vec(); }
+    match op {
+        Morphology::Dilate => disk_filter(mask, width, height, radius, true),
+        Morphology::Erode => disk_filter(mask, width, height, radius, false),
+        Morphology::Open => disk_filter(&disk_filter(mask, width, height, radius, false), width, height, radius, true),
+        Morphology::Close => disk_filter(&disk_filter(mask, width, height, radius, true), width, height, radius, false),
+    }
+}
+
+// Stands in for infinity so parabola intersections stay finite
+const FAR: f32 = 1e20;
+
+// Squared distance transform of one line in place (Felzenszwalb & Huttenlocher):
+// the lower envelope of parabolas rooted at each sample
+fn distance_transform_line(f: &mut [f32], hull: &mut [usize], boundaries: &mut [f32], output: &mut [f32]) {
+    let n = f.len();
+    let intersection = |p: usize, q: usize| ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * (q - p) as f32);
+    let mut k = 0;
+    hull[0] = 0;
+    boundaries[0] = -FAR;
+    boundaries[1] = FAR;
+    for q in 1..n {
+        let mut s = intersection(hull[k], q);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(hull[k], q);
+        }
+        k += 1;
+        hull[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = FAR;
+    }
+    k = 0;
+    for (q, out) in output[..n].iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f32 {
+            k += 1;
+        }
+        let p = hull[k];
+        *out = (q as f32 - p as f32).powi(2) + f[p];
+    }
+    f.copy_from_slice(&output[..n]);
+}
+
+// Euclidean distance from every pixel to the nearest pixel where `seed` is true
+pub fn distance_transform(seed: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let mut squared: Vec<f32> = seed.iter().map(|&s| if s { 0.0 } else { FAR }).collect();
+    let longest = width.max(height);
+    let mut line = vec![0.0f32; longest];
+    let mut hull = vec![0usize; longest];
+    let mut boundaries = vec![0.0f32; longest + 1];
+    let mut output = vec![0.0f32; longest];
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = squared[y * width + x];
+        }
+        distance_transform_line(&mut line[..height], &mut hull, &mut boundaries, &mut output);
+        for y in 0..height {
+            squared[y * width + x] = line[y];
+        }
+    }
+    for row in squared.chunks_exact_mut(width) {
+        distance_transform_line(row, &mut hull, &mut boundaries, &mut output);
+    }
+    squared.iter().map(|d| d.sqrt()).collect()
+}
+
+// Soft edge of the given radius around the half-coverage outline of a mask
+pub fn feather(mask: &[u8], width: usize, height: usize, radius: f32) -> Vec<u8> {
+    let inside: Vec<bool> = mask.iter().map(|&m| m >= 128).collect();
+    if inside.iter().all(|&i| i) || inside.iter().all(|&i| !i) {
+        return inside.iter().map(|&i| if i { 255 } else { 0 }).collect();
+    }
+    let outside: Vec<bool> = inside.iter().map(|&i| !i).collect();
+    let to_outside = distance_transform(&outside, width, height);
+    let to_inside = distance_transform(&inside, width, height);
+    // The outline runs halfway between the last pixel in and the first pixel out
+    let style = MaskStyle { feather: radius, invert: false };
+    inside
+        .iter()
+        .zip(to_outside.iter().zip(&to_inside))
+        .map(|(&i, (&d_out, &d_in))| style.coverage(if i { d_out - 0.5 } else { 0.5 - d_in }))
+        .collect()
+}
+
+// Combine the mask at other_ptr into the one at target_ptr (width * height bytes each)
+pub fn combine_masks(target_ptr: *mut u8, other_ptr: *const u8, width: usize, height: usize, op: MaskOp) {
+    let size = width * height;
+    let other = unsafe { std::slice::from_raw_parts(other_ptr, size) }.to_vec(); // Copied in case the buffers overlap
+    let target = unsafe { std::slice::from_raw_parts_mut(target_ptr, size) };
+    combine(target, &other, op);
+}
+
+pub fn invert_mask(mask_ptr: *mut u8, width: usize, height: usize) {
+    invert(unsafe { std::slice::from_raw_parts_mut(mask_ptr, width * height) });
+}
+
+// Morphology on the mask at mask_ptr, in place
+pub fn morphology_mask(mask_ptr: *mut u8, width: usize, height: usize, op: Morphology, radius: f32) {
+    let mask = unsafe { std::slice::from_raw_parts_mut(mask_ptr, width * height) };
+    let result = morphology(mask, width, height, op, radius);
+    mask.copy_from_slice(&result);
+}
+
+// Distance-transform feathering of the mask at mask_ptr, in place
+pub fn feather_mask(mask_ptr: *mut u8, width: usize, height: usize, radius: f32) {
+    let mask = unsafe { std::slice::from_raw_parts_mut(mask_ptr, width * height) };
+    let result = feather(mask, width, height, radius);
+    mask.copy_from_slice(&result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Covered area in pixels, counting partial coverage
+    fn area(mask: &[u8]) -> f32 {
+        mask.iter().map(|&m| m as f32 / 255.0).sum()
+    }
+
+    fn shape_mask(width: usize, height: usize, shape: &Shape, style: &MaskStyle) -> Vec<u8> {
+        let mut mask = vec![0u8; width * height];
+        draw_shape(&mut mask, width, height, shape, style);
+        mask
+    }
+
+    #[test]
+    fn shape_areas_match_their_geometry() {
+        let (width, height) = (80, 70);
+        let style = MaskStyle::default();
+        for angle in [0.0, 30.0, 90.0] {
+            let ellipse = Shape::Ellipse { cx: 40.0, cy: 35.0, rx: 25.0, ry: 14.0, angle };
+            let expected = std::f32::consts::PI * 25.0 * 14.0;
+            let covered = area(&shape_mask(width, height, &ellipse, &style));
+            assert!((covered - expected).abs() / expected < 0.01, "ellipse at {}: {} vs {}", angle, covered, expected);
+
+            let rect = Shape::RoundedRect { left: 15.0, top: 20.0, right: 65.0, bottom: 50.0, corner_radius: 8.0, angle };
+            let expected = 50.0 * 30.0 - (4.0 - std::f32::consts::PI) * 64.0;
+            let covered = area(&shape_mask(width, height, &rect, &style));
+            assert!((covered - expected).abs() / expected < 0.01, "rounded rect at {}: {} vs {}", angle, covered, expected);
+        }
+        let triangle = Shape::Polygon(vec![[10.0, 60.0], [70.0, 60.0], [40.0, 10.0]]);
+        let covered = area(&shape_mask(width, height, &triangle, &style));
+        assert!((covered - 1500.0).abs() < 15.0, "triangle: {}", covered);
+    }
+
+    #[test]
+    fn polygons_fill_even_odd() {
+        // A pentagram: the points are inside, the pentagon in the middle crosses twice and is outside
+        let (width, height) = (60, 60);
+        let points: Vec<[f32; 2]> = (0..5)
+            .map(|k| {
+                let angle = (k as f32 * 144.0 - 90.0).to_radians();
+                [30.0 + 25.0 * angle.cos(), 30.0 + 25.0 * angle.sin()]
+            })
+            .collect();
+        let mask = shape_mask(width, height, &Shape::Polygon(points), &MaskStyle::default());
+        assert_eq!(mask[30 * width + 30], 0);
+        assert_eq!(mask[9 * width + 30], 255, "top point");
+        // Fewer than three vertices cover nothing
+        let segment = Shape::Polygon(vec![[1.0, 1.0], [50.0, 50.0]]);
+        assert!(shape_mask(width, height, &segment, &MaskStyle::default()).iter().all(|&m| m == 0));
+    }
+
+    #[test]
+    fn inversion_and_feathering_shape_the_edge() {
+        let (width, height) = (64, 48);
+        let circle = Shape::Ellipse { cx: 32.0, cy: 24.0, rx: 15.0, ry: 15.0, angle: 0.0 };
+        let plain = shape_mask(width, height, &circle, &MaskStyle::default());
+        let inverted = shape_mask(width, height, &circle, &MaskStyle { invert: true, ..MaskStyle::default() });
+        assert!(plain.iter().zip(&inverted).all(|(&a, &b)| a as u16 + b as u16 == 255));
+
+        // The ramp is one pixel wide without feathering and twice the radius with it
+        let soft = shape_mask(width, height, &circle, &MaskStyle { feather: 4.0, invert: false });
+        let partial = |mask: &[u8]| mask[24 * width..25 * width].iter().filter(|&&m| m > 0 && m < 255).count();
+        assert!(partial(&plain) <= 2, "{}", partial(&plain));
+        assert!((14..=18).contains(&partial(&soft)), "{}", partial(&soft));
+        // The ramp is centered on the outline; on a circle of radius r feathered by f it covers pi (r^2 + f^2 / 3)
+        let expected = std::f32::consts::PI * (15.0 * 15.0 + 16.0 / 3.0);
+        assert!((area(&soft) - expected).abs() / expected < 0.01, "{} vs {}", area(&soft), expected);
+    }
+
+    // Deterministic pseudo-random hard mask with roughly `percent` coverage
+    fn random_mask(width: usize, height: usize, percent: usize, salt: usize) -> Vec<u8> {
+        (0..width * height).map(|i| if (((i + salt) * 2654435761) >> 9) % 100 < percent { 255 } else { 0 }).collect()
+    }
+
+    #[test]
+    fn distance_transform_matches_brute_force() {
+        let (width, height) = (17, 13);
+        for (percent, salt) in [(3, 0), (10, 7), (40, 1)] {
+            let seed: Vec<bool> = random_mask(width, height, percent, salt).iter().map(|&m| m == 255).collect();
+            let distances = distance_transform(&seed, width, height);
+            for (i, &d) in distances.iter().enumerate() {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                let nearest = (0..seed.len())
+                    .filter(|&j| seed[j])
+                    .map(|j| ((x - (j % width) as f32).powi(2) + (y - (j / width) as f32).powi(2)).sqrt())
+                    .fold(f32::INFINITY, f32::min);
+                assert!((d - nearest).abs() < 1e-3, "{}% at ({}, {}): {} vs {}", percent, x, y, d, nearest);
+            }
+        }
+    }
+
+    #[test]
+    fn set_operations_agree_with_boolean_logic_on_hard_masks() {
+        let (a, b) = (random_mask(23, 19, 50, 0), random_mask(23, 19, 50, 11));
+        let apply = |op| {
+            let mut result = a.clone();
+            combine(&mut result, &b, op);
+            result
+        };
+        let expected = |f: fn(bool, bool) -> bool| -> Vec<u8> {
+            a.iter().zip(&b).map(|(&x, &y)| if f(x == 255, y == 255) { 255 } else { 0 }).collect()
+        };
+        assert_eq!(apply(MaskOp::Union), expected(|x, y| x || y));
+        assert_eq!(apply(MaskOp::Intersection), expected(|x, y| x && y));
+        assert_eq!(apply(MaskOp::Subtract), expected(|x, y| x && !y));
+        assert_eq!(apply(MaskOp::Xor), expected(|x, y| x != y));
+
+        // On soft masks: De Morgan, and subtraction is intersection with the complement
+        let (soft_a, soft_b): (Vec<u8>, Vec<u8>) = ((0..=255).collect(), (0..=255).rev().collect());
+        let mut union = soft_a.clone();
+        combine(&mut union, &soft_b, MaskOp::Union);
+        invert(&mut union);
+        let (mut not_a, mut not_b) = (soft_a.clone(), soft_b.clone());
+        invert(&mut not_a);
+        invert(&mut not_b);
+        let mut both = not_a.clone();
+        combine(&mut both, &not_b, MaskOp::Intersection);
+        assert_eq!(union, both);
+        let mut subtract = soft_a.clone();
+        combine(&mut subtract, &soft_b, MaskOp::Subtract);
+        let mut with_complement = soft_a.clone();
+        combine(&mut with_complement, &not_b, MaskOp::Intersection);
+        assert_eq!(subtract, with_complement);
+    }
+
+    #[test]
+    fn dilating_a_point_draws_the_disk() {
+        let (width, height) = (31, 31);
+        let mut point = vec![0u8; width * height];
+        point[15 * width + 15] = 255;
+        for radius in [1.0f32, 2.5, 4.0, 7.3] {
+            let disk = morphology(&point, width, height, Morphology::Dilate, radius);
+            for (i, &m) in disk.iter().enumerate() {
+                let (dx, dy) = ((i % width) as f32 - 15.0, (i / width) as f32 - 15.0);
+                assert_eq!(m == 255, dx * dx + dy * dy <= radius * radius, "radius {} at ({}, {})", radius, dx, dy);
+            }
+        }
+    }
+
+    #[test]
+    fn morphology_is_dual_and_ordered() {
+        let (width, height) = (29, 21);
+        let mask = random_mask(width, height, 55, 3);
+        for radius in [1.0, 2.0, 3.5] {
+            // Erosion is dilation of the complement, complemented
+            let mut inverted = mask.clone();
+            invert(&mut inverted);
+            let mut dual = morphology(&inverted, width, height, Morphology::Dilate, radius);
+            invert(&mut dual);
+            assert_eq!(morphology(&mask, width, height, Morphology::Erode, radius), dual);
+
+            // erode <= open <= mask <= close <= dilate, pixel by pixel; opening and closing are idempotent
+            let apply = |op| morphology(&mask, width, height, op, radius);
+            let chain = [apply(Morphology::Erode), apply(Morphology::Open), mask.clone(), apply(Morphology::Close), apply(Morphology::Dilate)];
+            for pair in chain.windows(2) {
+                assert!(pair[0].iter().zip(&pair[1]).all(|(a, b)| a <= b), "radius {}", radius);
+            }
+            for (op, once) in [(Morphology::Open, &chain[1]), (Morphology::Close, &chain[3])] {
+                assert_eq!(&morphology(once, width, height, op, radius), once, "{:?} radius {}", op, radius);
+            }
+        }
+        // Masks touching the border are not eaten away
+        let full = vec![255u8; width * height];
+        assert_eq!(morphology(&full, width, height, Morphology::Erode, 3.0), full);
+    }
+
+    #[test]
+    fn feathering_keeps_the_half_coverage_outline() {
+        let (width, height) = (40, 30);
+        let mut mask = random_mask(width, height, 50, 5);
+        mask = morphology(&mask, width, height, Morphology::Open, 2.0);
+        for radius in [0.0, 1.5, 4.0] {
+            let soft = feather(&mask, width, height, radius);
+            for (i, (&hard, &soft)) in mask.iter().zip(&soft).enumerate() {
+                assert_eq!(hard >= 128, soft >= 128, "radius {} pixel {}", radius, i);
+            }
+        }
+    }
+}
@@ -4,6 +4,7 @@ use rand::Rng;
 // --- Enums --- 
 
 #[wasm_bindgen]
+#[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MultiToneAlgorithm {
     Ordered,
@@ -1,3 +1,5 @@
+use crate::supercell::{generate_threshold_array, DotShape, ThresholdArray};
+
 // Ordered dithering using Bayer matrices
 pub fn ordered_dither(
     input_ptr: *const u8,
@@ -44,4 +46,44 @@ pub fn ordered_dither(
             output[idx] = result;
         }
     }
-} 
\ No newline at end of file
+} 
+
+// Ordered dithering against a precomputed, tileable threshold array
+pub fn threshold_array_dither(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    thresholds: &ThresholdArray,
+    output: &mut [u8],
+) {
+    let size = thresholds.size;
+    if size == 0 { return; }
+
+    for y in 0..height {
+        let row = &thresholds.values[(y % size) * size..(y % size + 1) * size];
+        for x in 0..width {
+            let idx = y * width + x;
+            output[idx] = if input[idx] > row[x % size] { 255 } else { 0 };
+        }
+    }
+}
+
+// Clustered-dot ordered dithering with a supercell screen at a rational angle
+#[allow(clippy::too_many_arguments)]
+pub fn clustered_dot_dither(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    lpi: f32,
+    angle: f32,
+    dpi: f32,
+    shape: DotShape,
+    output_ptr: *mut u8,
+) {
+    // Convert the input and output pointers to slices
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, width * height) };
+
+    let thresholds = generate_threshold_array(lpi, angle, dpi, shape);
+    threshold_array_dither(input, width, height, &thresholds, output);
+}
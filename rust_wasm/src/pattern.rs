@@ -1,7 +1,10 @@
-"""use wasm_bindgen::prelude::*;
+use wasm_bindgen::prelude::*;
 use std::f32::consts::PI;
 
+use crate::vector::{VectorDot, VectorShape};
+
 #[wasm_bindgen]
+#[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PatternType {
     Dots,
@@ -56,6 +59,69 @@ pub fn pattern_dither(
     }
 }
 
+// Vector geometry for a pattern, covering exactly the pixels pattern_dither
+// turns white: each row's runs of white pixels become rectangles, and a run
+// that repeats with the same extent on the next row extends the rectangle
+// above it, so lines, crosses and the solid parts of dots come out as single
+// shapes instead of one per row.
+pub fn pattern_dots(
+    input: &[u8],
+    width: usize,
+    height: usize,
+    pattern_type: PatternType,
+    pattern_size: usize,
+) -> Vec<VectorDot> {
+    let mut dots = Vec::new();
+    if width == 0 || height == 0 { return dots; }
+    let size = pattern_size.max(1);
+    let pattern = get_pattern_matrix(pattern_type, size);
+
+    // Rectangles still open at the previous row: (first column, end column, first row)
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    let close = |dots: &mut Vec<VectorDot>, (start, end, top): (usize, usize, usize), bottom: usize| {
+        let (w, h) = ((end - start) as f32, (bottom - top) as f32);
+        dots.push(VectorDot {
+            shape: VectorShape::Rect,
+            cx: start as f32 + w / 2.0,
+            cy: top as f32 + h / 2.0,
+            rx: w / 2.0,
+            ry: h / 2.0,
+            rotation: 0.0,
+        });
+    };
+    for y in 0..=height {
+        // Runs of white pixels in this row, with the same comparison as pattern_dither
+        let mut runs = Vec::new();
+        if y < height {
+            let is_white = |x: usize| (input[y * width + x] as f32) >= pattern[y % size][x % size] * 255.0;
+            let mut x = 0;
+            while x < width {
+                if !is_white(x) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < width && is_white(x) {
+                    x += 1;
+                }
+                runs.push((start, x));
+            }
+        }
+        let mut next_open = Vec::with_capacity(runs.len());
+        for &(start, end) in &runs {
+            match open.iter().position(|&(s, e, _)| s == start && e == end) {
+                Some(i) => next_open.push(open.swap_remove(i)),
+                None => next_open.push((start, end, y)),
+            }
+        }
+        for rect in open.drain(..) {
+            close(&mut dots, rect, y);
+        }
+        open = next_open;
+    }
+    dots
+}
+
 // Get the pattern matrix based on type and size
 fn get_pattern_matrix(pattern_type: PatternType, size: usize) -> Vec<Vec<f32>> {
     // Ensure size is at least 1 to avoid division by zero or empty patterns
@@ -186,4 +252,60 @@ fn create_custom_pattern(size: usize) -> Vec<Vec<f32>> {
     pattern
 }
 
-"" 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_PATTERNS: [PatternType; 7] = [
+        PatternType::Dots,
+        PatternType::Lines,
+        PatternType::Crosses,
+        PatternType::Diamonds,
+        PatternType::Waves,
+        PatternType::Bricks,
+        PatternType::Custom,
+    ];
+
+    // Coverage count per pixel of axis-aligned rectangles, sampled at pixel centers
+    fn rasterize(dots: &[VectorDot], width: usize, height: usize) -> Vec<u8> {
+        let mut coverage = vec![0u8; width * height];
+        for dot in dots {
+            assert_eq!((dot.shape, dot.rotation), (VectorShape::Rect, 0.0));
+            for y in 0..height {
+                for x in 0..width {
+                    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                    if (px - dot.cx).abs() < dot.rx && (py - dot.cy).abs() < dot.ry {
+                        coverage[y * width + x] += 1;
+                    }
+                }
+            }
+        }
+        coverage
+    }
+
+    #[test]
+    fn geometry_matches_the_raster() {
+        let (width, height) = (23, 17);
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![0; width * height],
+            vec![37; width * height],
+            vec![128; width * height],
+            vec![255; width * height],
+            (0..width * height).map(|i| ((i % width) * 255 / (width - 1)) as u8).collect(),
+            (0..width * height).map(|i| ((i * 2654435761) >> 7) as u8).collect(),
+        ];
+        for pattern_type in ALL_PATTERNS {
+            for size in [1, 3, 4, 8] {
+                for input in &inputs {
+                    let mut raster = vec![0u8; width * height * 4];
+                    pattern_dither(input.as_ptr(), width, height, pattern_type, size, raster.as_mut_ptr());
+                    let coverage = rasterize(&pattern_dots(input, width, height, pattern_type, size), width, height);
+                    for i in 0..width * height {
+                        let expected = (raster[i * 4] == 255) as u8;
+                        assert_eq!(coverage[i], expected, "{:?} size {} pixel ({}, {})", pattern_type, size, i % width, i / width);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::multi_tone::MultiToneAlgorithm;
+use crate::selective::{dither_plane, AlgorithmParams, DitheringAlgorithmType};
+
+// Per-channel RGB dithering
+//
+// R, G and B are split into separate planes and each plane is dithered on its
+// own with any of the grayscale algorithms. More than two levels per channel
+// are reached by dithering only the fraction between the two nearest levels,
+// so every binary algorithm works unchanged. Mask-based algorithms would line
+// up their thresholds in all three channels (chroma banding), so their planes
+// are shifted and rotated against each other before dithering.
+
+// Offsets applied to the green and blue planes; odd and coprime with the
+// usual mask periods so the channels never share a threshold phase
+const GREEN_SHIFT: (usize, usize) = (13, 7);
+const BLUE_SHIFT: (usize, usize) = (5, 11);
+
+// Algorithms that threshold against a fixed mask and need decorrelating
+fn uses_threshold_mask(algo_type: DitheringAlgorithmType, params: &AlgorithmParams) -> bool {
+    match algo_type {
+        DitheringAlgorithmType::Ordered
+        | DitheringAlgorithmType::Pattern
+        | DitheringAlgorithmType::BlueNoise
+        | DitheringAlgorithmType::VoidAndCluster => true,
+        DitheringAlgorithmType::MultiTone => params.multi_tone_algo != MultiToneAlgorithm::ErrorDiffusion,
+        _ => false,
+    }
+}
+
+// Geometric change applied to a plane before dithering and undone afterwards
+#[derive(Clone, Copy, Debug)]
+struct PlaneTransform {
+    shift: (usize, usize),
+    rotate: bool, // Rotate 90 degrees clockwise before shifting
+}
+
+impl PlaneTransform {
+    const IDENTITY: PlaneTransform = PlaneTransform { shift: (0, 0), rotate: false };
+
+    // Dimensions of the transformed plane
+    fn dimensions(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.rotate { (height, width) } else { (width, height) }
+    }
+
+    // Index in the source plane of transformed pixel (tx, ty)
+    fn source_index(&self, tx: usize, ty: usize, width: usize, height: usize) -> usize {
+        let (t_width, t_height) = self.dimensions(width, height);
+        // Undo the cyclic shift first
+        let rx = (tx + self.shift.0) % t_width;
+        let ry = (ty + self.shift.1) % t_height;
+        if self.rotate {
+            // Clockwise rotation: transformed (rx, ry) came from source (ry, height - 1 - rx)
+            (height - 1 - rx) * width + ry
+        } else {
+            ry * width + rx
+        }
+    }
+
+    fn apply(&self, plane: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let (t_width, t_height) = self.dimensions(width, height);
+        let mut out = vec![0u8; plane.len()];
+        for ty in 0..t_height {
+            for tx in 0..t_width {
+                out[ty * t_width + tx] = plane[self.source_index(tx, ty, width, height)];
+            }
+        }
+        out
+    }
+
+    fn invert(&self, transformed: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let (t_width, t_height) = self.dimensions(width, height);
+        let mut out = vec![0u8; transformed.len()];
+        for ty in 0..t_height {
+            for tx in 0..t_width {
+                out[self.source_index(tx, ty, width, height)] = transformed[ty * t_width + tx];
+            }
+        }
+        out
+    }
+}
+
+// Dither one channel plane to `levels` evenly spaced values
+fn dither_channel(
+    plane: &[u8],
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: &AlgorithmParams,
+    levels: usize,
+    transform: PlaneTransform,
+) -> Vec<u8> {
+    let levels = levels.clamp(2, 256);
+    let step = 255.0 / (levels - 1) as f32;
+
+    // Split every value into the lower level and the fraction towards the next one
+    let mut base_levels = Vec::with_capacity(plane.len());
+    let mut fractions = Vec::with_capacity(plane.len());
+    for &value in plane {
+        let position = value as f32 / step;
+        let base = (position.floor() as usize).min(levels - 2);
+        base_levels.push(base);
+        fractions.push(((position - base as f32).clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+
+    // Binary-dither the fractions in the transformed frame
+    let (t_width, t_height) = transform.dimensions(width, height);
+    let transformed = transform.apply(&fractions, width, height);
+    let dithered = dither_plane(&transformed, t_width, t_height, algo_type, params);
+    let bits = transform.invert(&dithered, width, height);
+
+    base_levels
+        .iter()
+        .zip(bits.iter())
+        .map(|(&base, &bit)| {
+            let level = base + if bit > 127 { 1 } else { 0 };
+            (level as f32 * step).round() as u8
+        })
+        .collect()
+}
+
+// Dither R, G and B independently and write RGBA (alpha is copied from the input)
+#[allow(clippy::too_many_arguments)]
+pub fn rgb_channel_dither(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: &AlgorithmParams,
+    levels: usize,
+    decorrelate: bool,
+    output: &mut [u8],
+) {
+    let size = width * height;
+    if size == 0 { return; }
+
+    let transforms = if decorrelate && uses_threshold_mask(algo_type, params) {
+        [
+            PlaneTransform::IDENTITY,
+            PlaneTransform { shift: GREEN_SHIFT, rotate: false },
+            PlaneTransform { shift: BLUE_SHIFT, rotate: true },
+        ]
+    } else {
+        [PlaneTransform::IDENTITY; 3]
+    };
+
+    for (channel, &transform) in transforms.iter().enumerate() {
+        let plane: Vec<u8> = rgba.iter().skip(channel).step_by(4).copied().collect();
+        let result = dither_channel(&plane, width, height, algo_type, params, levels, transform);
+        for (i, &value) in result.iter().enumerate() {
+            output[i * 4 + channel] = value;
+        }
+    }
+    for i in 0..size {
+        output[i * 4 + 3] = rgba[i * 4 + 3];
+    }
+}
+
+// Per-channel RGB dithering of an RGBA buffer
+#[allow(clippy::too_many_arguments)]
+pub fn rgb_dither(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: &AlgorithmParams,
+    levels: usize,
+    decorrelate: bool,
+    output_ptr: *mut u8,
+) {
+    let size = width * height;
+    let rgba = unsafe { std::slice::from_raw_parts(rgba_ptr, size * 4) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) };
+    rgb_channel_dither(rgba, width, height, algo_type, params, levels, decorrelate, output);
+}
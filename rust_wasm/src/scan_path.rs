@@ -0,0 +1,258 @@
+use wasm_bindgen::prelude::*;
+
+// Scan paths: orders in which an algorithm visits the pixels of an image
+//
+// Every path visits each pixel of a width x height image exactly once. Raster,
+// serpentine and the generalized Hilbert ("gilbert") curve fit any rectangle.
+// The Hilbert, Peano and Z-order curves are defined on power-of-two (Peano:
+// power-of-three) squares, so they are generated for the smallest square
+// covering the image and clipped, which skips across the padding; gilbert is
+// the Hilbert curve without that waste. Z-order is not continuous at all and
+// jumps between quadrants.
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanPath {
+    Raster = 0,     // Rows left to right, top to bottom
+    Serpentine = 1, // Rows alternating direction (boustrophedon)
+    Hilbert = 2,    // Hilbert curve on the covering power-of-two square
+    Gilbert = 3,    // Generalized Hilbert curve on the exact rectangle
+    Peano = 4,      // Peano curve on the covering power-of-three square
+    ZOrder = 5,     // Morton order on the covering power-of-two square
+}
+
+// Pixel coordinates in visiting order
+pub fn scan_path(path: ScanPath, width: usize, height: usize) -> Vec<(usize, usize)> {
+    if width == 0 || height == 0 { return Vec::new(); }
+    match path {
+        ScanPath::Raster => (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).collect(),
+        ScanPath::Serpentine => (0..height)
+            .flat_map(|y| (0..width).map(move |i| (if y % 2 == 0 { i } else { width - 1 - i }, y)))
+            .collect(),
+        ScanPath::Hilbert => hilbert_curve(width, height),
+        ScanPath::Gilbert => gilbert_curve(width, height),
+        ScanPath::Peano => peano_curve(width, height),
+        ScanPath::ZOrder => z_order(width, height),
+    }
+}
+
+// Hilbert curve over the covering power-of-two square, clipped to the image
+fn hilbert_curve(width: usize, height: usize) -> Vec<(usize, usize)> {
+    let side = width.max(height).next_power_of_two();
+    let mut points = Vec::with_capacity(width * height);
+    for d in 0..side * side {
+        // Index to coordinates, one quadrant level at a time
+        let (mut x, mut y, mut t) = (0, 0, d);
+        let mut s = 1;
+        while s < side {
+            let rx = (t / 2) & 1;
+            let ry = (t ^ rx) & 1;
+            if ry == 0 {
+                if rx == 1 {
+                    x = s - 1 - x;
+                    y = s - 1 - y;
+                }
+                std::mem::swap(&mut x, &mut y);
+            }
+            x += s * rx;
+            y += s * ry;
+            t /= 4;
+            s *= 2;
+        }
+        if x < width && y < height {
+            points.push((x, y));
+        }
+    }
+    points
+}
+
+// Visit every pixel of a width x height rectangle along a gilbert curve. Steps are
+// between neighbouring pixels except for one diagonal step when the longer side
+// is odd and the shorter side even (and not 2): the curve runs between the two
+// corners of the longer side, which a path of unit steps cannot join on such a
+// grid, so this is inherent to gilbert2d.
+pub fn gilbert_curve(width: usize, height: usize) -> Vec<(usize, usize)> {
+    let mut points = Vec::with_capacity(width * height);
+    if width == 0 || height == 0 { return points; }
+    let (w, h) = (width as isize, height as isize);
+    if width >= height {
+        gilbert_recursive(&mut points, 0, 0, w, 0, 0, h);
+    } else {
+        gilbert_recursive(&mut points, 0, 0, 0, h, w, 0);
+    }
+    points
+}
+
+// Fill the rectangle at (x, y) spanned by the major axis (ax, ay) and minor axis (bx, by)
+// (Jakub Červený's gilbert2d)
+fn gilbert_recursive(
+    points: &mut Vec<(usize, usize)>,
+    x: isize, y: isize,
+    ax: isize, ay: isize,
+    bx: isize, by: isize,
+) {
+    let w = (ax + ay).abs();
+    let h = (bx + by).abs();
+    let (dax, day) = (ax.signum(), ay.signum()); // Unit major direction
+    let (dbx, dby) = (bx.signum(), by.signum()); // Unit minor direction
+
+    // A single row or column is walked straight
+    if h == 1 || w == 1 {
+        let (count, dx, dy) = if h == 1 { (w, dax, day) } else { (h, dbx, dby) };
+        for i in 0..count {
+            points.push(((x + i * dx) as usize, (y + i * dy) as usize));
+        }
+        return;
+    }
+
+    let (mut ax2, mut ay2) = (ax.div_euclid(2), ay.div_euclid(2));
+    let (mut bx2, mut by2) = (bx.div_euclid(2), by.div_euclid(2));
+    let w2 = (ax2 + ay2).abs();
+    let h2 = (bx2 + by2).abs();
+
+    if 2 * w > 3 * h {
+        // Long rectangle: split in two along the major axis, keeping the halves even so the curve can turn
+        if w2 % 2 == 1 && w > 2 {
+            ax2 += dax;
+            ay2 += day;
+        }
+        gilbert_recursive(points, x, y, ax2, ay2, bx, by);
+        gilbert_recursive(points, x + ax2, y + ay2, ax - ax2, ay - ay2, bx, by);
+    } else {
+        // Otherwise split in three: up the first half of the minor axis, across, and back down
+        if h2 % 2 == 1 && h > 2 {
+            bx2 += dbx;
+            by2 += dby;
+        }
+        gilbert_recursive(points, x, y, bx2, by2, ax2, ay2);
+        gilbert_recursive(points, x + bx2, y + by2, ax, ay, bx - bx2, by - by2);
+        gilbert_recursive(
+            points,
+            x + (ax - dax) + (bx2 - dbx),
+            y + (ay - day) + (by2 - dby),
+            -bx2, -by2,
+            -(ax - ax2), -(ay - ay2),
+        );
+    }
+}
+
+// Peano curve over the covering power-of-three square, clipped to the image
+fn peano_curve(width: usize, height: usize) -> Vec<(usize, usize)> {
+    let mut side = 1;
+    while side < width.max(height) {
+        side *= 3;
+    }
+    let mut points = Vec::with_capacity(width * height);
+    peano_recursive(&mut points, 0, 0, side, false, false, width, height);
+    points
+}
+
+// The 3x3 blocks of a square are visited column by column in a serpentine,
+// each block mirrored so it starts next to where the previous one ended
+#[allow(clippy::too_many_arguments)]
+fn peano_recursive(
+    points: &mut Vec<(usize, usize)>,
+    x: usize, y: usize,
+    side: usize,
+    flip_x: bool, flip_y: bool,
+    width: usize, height: usize,
+) {
+    if x >= width || y >= height { return; } // Entirely in the padding
+    if side == 1 {
+        points.push((x, y));
+        return;
+    }
+    let third = side / 3;
+    for k in 0..9 {
+        let column = k / 3;
+        let row = if column % 2 == 0 { k % 3 } else { 2 - k % 3 };
+        let bx = if flip_x { 2 - column } else { column };
+        let by = if flip_y { 2 - row } else { row };
+        peano_recursive(
+            points,
+            x + bx * third,
+            y + by * third,
+            third,
+            flip_x ^ (row % 2 == 1),
+            flip_y ^ (column % 2 == 1),
+            width,
+            height,
+        );
+    }
+}
+
+// Morton (Z) order over the covering power-of-two square, clipped to the image
+fn z_order(width: usize, height: usize) -> Vec<(usize, usize)> {
+    let side = width.max(height).next_power_of_two();
+    // Every other bit of d, starting at bit `offset`
+    let compact = |d: usize, offset: usize| {
+        let mut value = 0;
+        let mut bit = 0;
+        while (1usize << (2 * bit + offset)) <= d {
+            value |= ((d >> (2 * bit + offset)) & 1) << bit;
+            bit += 1;
+        }
+        value
+    };
+    (0..side * side)
+        .map(|d| (compact(d, 0), compact(d, 1)))
+        .filter(|&(x, y)| x < width && y < height)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_PATHS: [ScanPath; 6] =
+        [ScanPath::Raster, ScanPath::Serpentine, ScanPath::Hilbert, ScanPath::Gilbert, ScanPath::Peano, ScanPath::ZOrder];
+
+    fn is_unit_step(a: (usize, usize), b: (usize, usize)) -> bool {
+        a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1
+    }
+
+    #[test]
+    fn every_path_visits_each_pixel_once() {
+        let sizes = [(1, 1), (1, 7), (7, 1), (2, 3), (5, 3), (3, 5), (10, 6), (17, 4), (9, 27), (30, 31), (64, 64)];
+        for path in ALL_PATHS {
+            for &(width, height) in &sizes {
+                let points = scan_path(path, width, height);
+                assert_eq!(points.len(), width * height, "{:?} {}x{}", path, width, height);
+                let mut seen = vec![false; width * height];
+                for &(x, y) in &points {
+                    assert!(x < width && y < height, "{:?} {}x{} left the image at ({}, {})", path, width, height, x, y);
+                    assert!(!seen[y * width + x], "{:?} {}x{} visited ({}, {}) twice", path, width, height, x, y);
+                    seen[y * width + x] = true;
+                }
+            }
+            assert!(scan_path(path, 0, 5).is_empty());
+        }
+    }
+
+    #[test]
+    fn curves_move_in_unit_steps_on_their_native_squares() {
+        for size in [2, 4, 8, 16, 32] {
+            let points = scan_path(ScanPath::Hilbert, size, size);
+            assert!(points.windows(2).all(|s| is_unit_step(s[0], s[1])), "Hilbert {}", size);
+        }
+        for size in [3, 9, 27] {
+            let points = scan_path(ScanPath::Peano, size, size);
+            assert!(points.windows(2).all(|s| is_unit_step(s[0], s[1])), "Peano {}", size);
+        }
+    }
+
+    #[test]
+    fn gilbert_steps_are_unit_except_one_diagonal() {
+        for width in 1..24 {
+            for height in 1..24 {
+                let points = gilbert_curve(width, height);
+                let diagonal = points.windows(2).filter(|s| !is_unit_step(s[0], s[1])).count();
+                assert!(points.windows(2).all(|s| s[0].0.abs_diff(s[1].0) <= 1 && s[0].1.abs_diff(s[1].1) <= 1));
+                let (long, short) = (width.max(height), width.min(height));
+                let expected = (long % 2 == 1 && short % 2 == 0 && short != 2) as usize;
+                assert_eq!(diagonal, expected, "{}x{}", width, height);
+            }
+        }
+    }
+}
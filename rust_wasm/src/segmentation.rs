@@ -0,0 +1,560 @@
+use wasm_bindgen::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+use crate::edge_detection::{find_edges, EdgeOptions};
+use crate::line_art::stroke_mask;
+
+// Automatic region segmentation for selective dithering
+//
+// Every method labels each pixel of an RGBA image with a region number, and
+// masks for selective_dither are read off the label map. Colors are compared in
+// CIELAB so distances follow perceived difference.
+// - SLIC superpixels: k-means over color and position, each center searching
+//   only a window twice the grid step around itself, so regions stay compact.
+// - K-means color clustering: regions are color classes and need not be
+//   connected; a region is every pixel of one cluster.
+// - Edge regions: connected areas of non-edge pixels (edge_detection.rs), with
+//   the edge pixels then handed to the nearest area.
+// Afterwards connected pieces smaller than the minimum region size are merged
+// into the neighbouring piece closest in mean color, smallest first.
+
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentationMethod {
+    Slic = 0,
+    KMeans = 1,
+    EdgeRegions = 2,
+}
+
+// Settings for segment; each method ignores the ones it does not use
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SegmentationOptions {
+    pub method: SegmentationMethod,
+    pub regions: usize,         // SLIC: approximate superpixel count; k-means: cluster count
+    pub compactness: f32,       // SLIC: weight of position against color (Lab units per grid step)
+    pub iterations: usize,      // SLIC and k-means refinement passes
+    pub edges: EdgeOptions,     // Edge regions: the detector drawing the boundaries
+    pub min_region_size: usize, // Pixels; smaller connected pieces are merged into a neighbour
+    pub seed: u64,              // K-means initial centers
+}
+
+impl Default for SegmentationOptions {
+    fn default() -> Self {
+        SegmentationOptions {
+            method: SegmentationMethod::Slic,
+            regions: 64,
+            compactness: 10.0,
+            iterations: 10,
+            edges: EdgeOptions::default(),
+            min_region_size: 64,
+            seed: 0,
+        }
+    }
+}
+
+// A label per pixel, numbered 0..count
+#[derive(Clone, Debug, Default)]
+pub struct Segmentation {
+    pub labels: Vec<u32>,
+    pub count: usize,
+}
+
+impl Segmentation {
+    // Inclusive bounding box (x1, y1, x2, y2) of every region
+    pub fn bounds(&self, width: usize) -> Vec<[usize; 4]> {
+        let mut bounds = vec![[usize::MAX, usize::MAX, 0, 0]; self.count];
+        for (i, &label) in self.labels.iter().enumerate() {
+            let (x, y) = (i % width, i / width);
+            let Some(b) = bounds.get_mut(label as usize) else { continue };
+            b[0] = b[0].min(x);
+            b[1] = b[1].min(y);
+            b[2] = b[2].max(x);
+            b[3] = b[3].max(y);
+        }
+        bounds
+    }
+}
+
+// sRGB to CIELAB (D65)
+fn srgb_to_lab(r: u8, g: u8, b: u8) -> [f32; 3] {
+    let linear = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let x = f((0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505);
+    let y = f(0.2126 * r + 0.7152 * g + 0.0722 * b);
+    let z = f((0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.0890);
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+fn color_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+// SLIC superpixels; centers start on a grid, nudged off edges to the lowest-gradient neighbour
+fn slic(lab: &[[f32; 3]], width: usize, height: usize, regions: usize, compactness: f32, iterations: usize) -> Vec<u32> {
+    let step = ((width * height) as f32 / regions.max(1) as f32).sqrt().max(1.0);
+    let columns = ((width as f32 / step).round() as usize).max(1);
+    let rows = ((height as f32 / step).round() as usize).max(1);
+    let at = |x: isize, y: isize| {
+        lab[y.clamp(0, height as isize - 1) as usize * width + x.clamp(0, width as isize - 1) as usize]
+    };
+    let gradient = |x: isize, y: isize| color_distance(&at(x + 1, y), &at(x - 1, y)) + color_distance(&at(x, y + 1), &at(x, y - 1));
+
+    // (L, a, b, x, y) of every center
+    let mut centers: Vec<[f32; 5]> = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let x = ((column as f32 + 0.5) * width as f32 / columns as f32) as isize;
+            let y = ((row as f32 + 0.5) * height as f32 / rows as f32) as isize;
+            let (mut best, mut best_gradient) = ((x, y), f32::MAX);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { continue; }
+                    let g = gradient(nx, ny);
+                    if g < best_gradient {
+                        best = (nx, ny);
+                        best_gradient = g;
+                    }
+                }
+            }
+            let c = at(best.0, best.1);
+            centers.push([c[0], c[1], c[2], best.0 as f32, best.1 as f32]);
+        }
+    }
+
+    let spatial_weight = (compactness / step).powi(2);
+    let reach = step.ceil() as isize;
+    let mut labels = vec![0u32; width * height];
+    let mut distances = vec![f32::MAX; width * height];
+    for _ in 0..iterations.max(1) {
+        distances.iter_mut().for_each(|d| *d = f32::MAX);
+        for (k, center) in centers.iter().enumerate() {
+            let (cx, cy) = (center[3].round() as isize, center[4].round() as isize);
+            for y in (cy - reach).max(0)..(cy + reach + 1).min(height as isize) {
+                for x in (cx - reach).max(0)..(cx + reach + 1).min(width as isize) {
+                    let i = y as usize * width + x as usize;
+                    let color = color_distance(&lab[i], &[center[0], center[1], center[2]]);
+                    let space = (x as f32 - center[3]).powi(2) + (y as f32 - center[4]).powi(2);
+                    let d = color + space * spatial_weight;
+                    if d < distances[i] {
+                        distances[i] = d;
+                        labels[i] = k as u32;
+                    }
+                }
+            }
+        }
+
+        // Move each center to the mean of its pixels; centers that lost all of them stay put
+        let mut sums = vec![[0.0f64; 6]; centers.len()];
+        for (i, &label) in labels.iter().enumerate() {
+            let s = &mut sums[label as usize];
+            let c = lab[i];
+            s[0] += c[0] as f64;
+            s[1] += c[1] as f64;
+            s[2] += c[2] as f64;
+            s[3] += (i % width) as f64;
+            s[4] += (i / width) as f64;
+            s[5] += 1.0;
+        }
+        for (center, s) in centers.iter_mut().zip(&sums) {
+            if s[5] > 0.0 {
+                for c in 0..5 {
+                    center[c] = (s[c] / s[5]) as f32;
+                }
+            }
+        }
+    }
+    labels
+}
+
+// K-means over colors, seeded with k-means++
+fn kmeans(lab: &[[f32; 3]], clusters: usize, iterations: usize, seed: u64) -> Vec<u32> {
+    let clusters = clusters.clamp(1, lab.len());
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Each new center is drawn with probability proportional to the squared distance to the nearest one
+    let mut centers: Vec<[f32; 3]> = vec![lab[rng.gen_range(0..lab.len())]];
+    let mut nearest: Vec<f32> = lab.iter().map(|c| color_distance(c, &centers[0])).collect();
+    while centers.len() < clusters {
+        let total: f64 = nearest.iter().map(|&d| d as f64).sum();
+        if total <= 0.0 { break; } // Fewer distinct colors than clusters
+        let mut target = rng.gen::<f64>() * total;
+        let mut chosen = lab.len() - 1;
+        for (i, &d) in nearest.iter().enumerate() {
+            target -= d as f64;
+            if target <= 0.0 && d > 0.0 {
+                chosen = i;
+                break;
+            }
+        }
+        let center = lab[chosen];
+        for (n, c) in nearest.iter_mut().zip(lab) {
+            *n = n.min(color_distance(c, &center));
+        }
+        centers.push(center);
+    }
+
+    let mut labels = vec![0u32; lab.len()];
+    for iteration in 0..iterations.max(1) {
+        let mut changed = false;
+        for (label, c) in labels.iter_mut().zip(lab) {
+            let best = (0..centers.len())
+                .min_by(|&a, &b| color_distance(c, &centers[a]).total_cmp(&color_distance(c, &centers[b])))
+                .unwrap_or(0) as u32;
+            changed |= *label != best;
+            *label = best;
+        }
+        if !changed && iteration > 0 { break; }
+        let mut sums = vec![[0.0f64; 4]; centers.len()];
+        for (&label, c) in labels.iter().zip(lab) {
+            let s = &mut sums[label as usize];
+            s[0] += c[0] as f64;
+            s[1] += c[1] as f64;
+            s[2] += c[2] as f64;
+            s[3] += 1.0;
+        }
+        for (center, s) in centers.iter_mut().zip(&sums) {
+            if s[3] > 0.0 {
+                *center = [(s[0] / s[3]) as f32, (s[1] / s[3]) as f32, (s[2] / s[3]) as f32];
+            }
+        }
+    }
+    labels
+}
+
+// Connected areas between detected edges; edge pixels join the nearest area.
+// Edges are found in lightness and in both color channels, so isoluminant
+// color boundaries still separate regions.
+fn edge_regions(lab: &[[f32; 3]], width: usize, height: usize, options: &EdgeOptions) -> Vec<u32> {
+    // L* scaled to 0-255 like a gray image; a* and b* one level per Lab unit around 128
+    let mut edges = vec![0u8; lab.len()];
+    for (channel, scale, offset) in [(0, 2.55, 0.0), (1, 1.0, 128.0), (2, 1.0, 128.0)] {
+        let plane: Vec<u8> = lab.iter().map(|c| (c[channel] * scale + offset).round().clamp(0.0, 255.0) as u8).collect();
+        let (found, _) = find_edges(&plane, width, height, options);
+        for (e, f) in edges.iter_mut().zip(found) {
+            *e |= f;
+        }
+    }
+    // Thickened by a pixel so the small gaps Canny leaves at junctions do not join areas
+    let open: Vec<bool> = stroke_mask(&edges, width, height, 3.0).iter().map(|&e| !e).collect();
+    if open.iter().all(|&o| !o) {
+        return vec![0; width * height];
+    }
+    let mut labels = connected_components(width, height, |a, b| open[a] && open[b]);
+
+    // Grow the areas over the edge pixels one ring at a time
+    let mut queue: VecDeque<usize> = (0..labels.len()).filter(|&i| open[i]).collect();
+    let mut assigned = open;
+    while let Some(i) = queue.pop_front() {
+        let (x, y) = (i % width, i / width);
+        for (nx, ny) in neighbours(x, y, width, height) {
+            let n = ny * width + nx;
+            if !assigned[n] {
+                assigned[n] = true;
+                labels[n] = labels[i];
+                queue.push_back(n);
+            }
+        }
+    }
+    labels
+}
+
+// The 4-connected neighbours of (x, y) inside the image
+fn neighbours(x: usize, y: usize, width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+    let candidates = [
+        (x > 0).then(|| (x - 1, y)),
+        (x + 1 < width).then(|| (x + 1, y)),
+        (y > 0).then(|| (x, y - 1)),
+        (y + 1 < height).then(|| (x, y + 1)),
+    ];
+    candidates.into_iter().flatten()
+}
+
+// 4-connected components of pixels joined by `connected`, numbered in raster order
+fn connected_components(width: usize, height: usize, connected: impl Fn(usize, usize) -> bool) -> Vec<u32> {
+    let mut labels = vec![u32::MAX; width * height];
+    let mut next = 0;
+    let mut stack = Vec::new();
+    for start in 0..labels.len() {
+        if labels[start] != u32::MAX { continue; }
+        labels[start] = next;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            for (nx, ny) in neighbours(i % width, i / width, width, height) {
+                let n = ny * width + nx;
+                if labels[n] == u32::MAX && connected(i, n) {
+                    labels[n] = next;
+                    stack.push(n);
+                }
+            }
+        }
+        next += 1;
+    }
+    labels
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+// Merge connected pieces under min_size pixels into their closest-colored neighbour.
+// With keep_classes the result keeps the input labels (k-means classes), otherwise
+// every connected piece becomes its own region.
+fn merge_small_regions(
+    labels: &[u32],
+    lab: &[[f32; 3]],
+    width: usize,
+    height: usize,
+    min_size: usize,
+    keep_classes: bool,
+) -> Segmentation {
+    let pieces = connected_components(width, height, |a, b| labels[a] == labels[b]);
+    let count = pieces.iter().map(|&p| p as usize + 1).max().unwrap_or(0);
+    let mut class = vec![0u32; count];
+    let mut size = vec![0usize; count];
+    let mut sum = vec![[0.0f64; 3]; count];
+    let mut adjacent: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (i, &p) in pieces.iter().enumerate() {
+        let p = p as usize;
+        class[p] = labels[i];
+        size[p] += 1;
+        for c in 0..3 {
+            sum[p][c] += lab[i][c] as f64;
+        }
+        let (x, y) = (i % width, i / width);
+        for n in [(x + 1 < width).then(|| i + 1), (y + 1 < height).then(|| i + width)].into_iter().flatten() {
+            let q = pieces[n] as usize;
+            if q != p {
+                adjacent[p].push(q);
+                adjacent[q].push(p);
+            }
+        }
+    }
+    for list in &mut adjacent {
+        list.sort_unstable();
+        list.dedup();
+    }
+
+    let mean = |s: &[f64; 3], n: usize| [(s[0] / n as f64) as f32, (s[1] / n as f64) as f32, (s[2] / n as f64) as f32];
+    let mut parent: Vec<usize> = (0..count).collect();
+    let mut order: Vec<usize> = (0..count).filter(|&p| size[p] < min_size).collect();
+    order.sort_by_key(|&p| size[p]);
+    let mut queue: VecDeque<usize> = order.into();
+    while let Some(p) = queue.pop_front() {
+        let root = find(&mut parent, p);
+        if size[root] >= min_size { continue; }
+        let color = mean(&sum[root], size[root]);
+        let mut best: Option<(usize, f32)> = None;
+        for &neighbour in &adjacent[root] {
+            let q = find(&mut parent, neighbour);
+            if q == root { continue; }
+            let d = color_distance(&color, &mean(&sum[q], size[q]));
+            if best.is_none_or(|(_, bd)| d < bd) {
+                best = Some((q, d));
+            }
+        }
+        let Some((target, _)) = best else { continue }; // The only region left
+        parent[root] = target;
+        size[target] += size[root];
+        let moved_sum = sum[root];
+        for (t, m) in sum[target].iter_mut().zip(moved_sum) {
+            *t += m;
+        }
+        let moved = std::mem::take(&mut adjacent[root]);
+        adjacent[target].extend(moved);
+        if size[target] < min_size {
+            queue.push_back(target);
+        }
+    }
+
+    // Number the surviving regions (or classes) in order of first appearance
+    let mut numbering: Vec<u32> = vec![u32::MAX; if keep_classes { labels.iter().map(|&l| l as usize + 1).max().unwrap_or(0) } else { count }];
+    let mut next = 0;
+    let mut result = vec![0u32; labels.len()];
+    for (i, &p) in pieces.iter().enumerate() {
+        let root = find(&mut parent, p as usize);
+        let key = if keep_classes { class[root] as usize } else { root };
+        if numbering[key] == u32::MAX {
+            numbering[key] = next;
+            next += 1;
+        }
+        result[i] = numbering[key];
+    }
+    Segmentation { labels: result, count: next as usize }
+}
+
+// Segment an RGBA image into labeled regions
+pub fn segment(rgba: &[u8], width: usize, height: usize, options: &SegmentationOptions) -> Segmentation {
+    if width == 0 || height == 0 { return Segmentation::default(); }
+    let lab: Vec<[f32; 3]> = rgba.chunks_exact(4).map(|p| srgb_to_lab(p[0], p[1], p[2])).collect();
+    let labels = match options.method {
+        SegmentationMethod::Slic => slic(&lab, width, height, options.regions, options.compactness, options.iterations),
+        SegmentationMethod::KMeans => kmeans(&lab, options.regions, options.iterations, options.seed),
+        SegmentationMethod::EdgeRegions => edge_regions(&lab, width, height, &options.edges),
+    };
+    let keep_classes = options.method == SegmentationMethod::KMeans;
+    merge_small_regions(&labels, &lab, width, height, options.min_region_size.max(1), keep_classes)
+}
+
+// Segment an RGBA image, writing one u32 label per pixel; returns the region count
+pub fn segment_image(
+    rgba_ptr: *const u8,
+    width: usize,
+    height: usize,
+    options: &SegmentationOptions,
+    labels_ptr: *mut u32,
+) -> usize {
+    let size = width * height;
+    let rgba = unsafe { std::slice::from_raw_parts(rgba_ptr, size * 4) };
+    let labels = unsafe { std::slice::from_raw_parts_mut(labels_ptr, size) };
+    let segmentation = segment(rgba, width, height, options);
+    labels.copy_from_slice(&segmentation.labels);
+    segmentation.count
+}
+
+// Mask of one region of a label map (255 inside, 0 outside), ready for selective_dither
+pub fn region_mask(labels_ptr: *const u32, width: usize, height: usize, label: u32, mask_ptr: *mut u8) {
+    let size = width * height;
+    let labels = unsafe { std::slice::from_raw_parts(labels_ptr, size) };
+    let mask = unsafe { std::slice::from_raw_parts_mut(mask_ptr, size) };
+    for (m, &l) in mask.iter_mut().zip(labels) {
+        *m = if l == label { 255 } else { 0 };
+    }
+}
+
+// Inclusive bounding boxes of `count` regions, four u32 (x1, y1, x2, y2) per region
+pub fn region_bounds(labels_ptr: *const u32, width: usize, height: usize, count: usize, bounds_ptr: *mut u32) {
+    let size = width * height;
+    let labels = unsafe { std::slice::from_raw_parts(labels_ptr, size) };
+    let output = unsafe { std::slice::from_raw_parts_mut(bounds_ptr, count * 4) };
+    let segmentation = Segmentation { labels: labels.to_vec(), count };
+    for (out, b) in output.chunks_exact_mut(4).zip(segmentation.bounds(width)) {
+        for (o, &v) in out.iter_mut().zip(&b) {
+            *o = v as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_METHODS: [SegmentationMethod; 3] =
+        [SegmentationMethod::Slic, SegmentationMethod::KMeans, SegmentationMethod::EdgeRegions];
+
+    // Four colored quadrants with a little deterministic noise
+    fn quadrants(width: usize, height: usize) -> Vec<u8> {
+        let colors = [[200, 40, 40], [40, 160, 60], [50, 60, 200], [230, 220, 120]];
+        (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                let color = colors[(x >= width / 2) as usize + 2 * (y >= height / 2) as usize];
+                let noise = ((i * 2654435761) >> 11) % 9;
+                [color[0] + noise as u8, color[1] + noise as u8, color[2] + noise as u8, 255]
+            })
+            .collect()
+    }
+
+    // Pixels of each label reached by a flood fill from its first pixel
+    fn is_connected(labels: &[u32], width: usize, height: usize, label: u32) -> bool {
+        let Some(start) = labels.iter().position(|&l| l == label) else { return false };
+        let mut seen = vec![false; labels.len()];
+        let mut stack = vec![start];
+        seen[start] = true;
+        let mut reached = 0;
+        while let Some(i) = stack.pop() {
+            reached += 1;
+            for (nx, ny) in neighbours(i % width, i / width, width, height) {
+                let n = ny * width + nx;
+                if labels[n] == label && !seen[n] {
+                    seen[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        reached == labels.iter().filter(|&&l| l == label).count()
+    }
+
+    #[test]
+    fn labels_cover_every_pixel_with_dense_numbers() {
+        let (width, height) = (48, 40);
+        let rgba = quadrants(width, height);
+        for method in ALL_METHODS {
+            let options = SegmentationOptions { method, regions: 12, min_region_size: 20, ..SegmentationOptions::default() };
+            let segmentation = segment(&rgba, width, height, &options);
+            assert_eq!(segmentation.labels.len(), width * height);
+            let mut sizes = vec![0usize; segmentation.count];
+            for &label in &segmentation.labels {
+                sizes[label as usize] += 1;
+            }
+            assert!(sizes.iter().all(|&n| n >= 20), "{:?}: region sizes {:?}", method, sizes);
+            if method != SegmentationMethod::KMeans {
+                for label in 0..segmentation.count as u32 {
+                    assert!(is_connected(&segmentation.labels, width, height, label), "{:?}: region {} is split", method, label);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn regions_follow_strong_color_boundaries() {
+        let (width, height) = (48, 40);
+        let rgba = quadrants(width, height);
+        let quadrant = |i: usize| (i % width >= width / 2) as usize + 2 * (i / width >= height / 2) as usize;
+        for method in ALL_METHODS {
+            // Blurred by the default sigma, these boundaries peak just under the default
+            // Canny threshold of 64, so edge regions gets a more sensitive detector
+            let regions = if method == SegmentationMethod::KMeans { 4 } else { 16 };
+            let edges = EdgeOptions { threshold: 32.0, low_threshold: 16.0, ..EdgeOptions::default() };
+            let options = SegmentationOptions { method, regions, edges, ..SegmentationOptions::default() };
+            let segmentation = segment(&rgba, width, height, &options);
+            // Edge regions hand the boundary pixels to a side, so only look at pixels off it
+            let interior = |i: usize| {
+                let (x, y) = (i % width, i / width);
+                method != SegmentationMethod::EdgeRegions || (x.abs_diff(width / 2) > 2 && y.abs_diff(height / 2) > 2)
+            };
+            let mut owner = vec![usize::MAX; segmentation.count];
+            for (i, &label) in segmentation.labels.iter().enumerate().filter(|&(i, _)| interior(i)) {
+                let owner = &mut owner[label as usize];
+                if *owner == usize::MAX {
+                    *owner = quadrant(i);
+                }
+                assert_eq!(*owner, quadrant(i), "{:?}: region {} crosses quadrants", method, label);
+            }
+        }
+        // With one cluster per color, k-means finds exactly the quadrants
+        let options = SegmentationOptions { method: SegmentationMethod::KMeans, regions: 4, ..SegmentationOptions::default() };
+        assert_eq!(segment(&rgba, width, height, &options).count, 4);
+    }
+
+    #[test]
+    fn bounds_enclose_every_region_pixel() {
+        let (width, height) = (30, 26);
+        let options = SegmentationOptions { regions: 9, min_region_size: 10, ..SegmentationOptions::default() };
+        let segmentation = segment(&quadrants(width, height), width, height, &options);
+        let bounds = segmentation.bounds(width);
+        for (i, &label) in segmentation.labels.iter().enumerate() {
+            let [x1, y1, x2, y2] = bounds[label as usize];
+            let (x, y) = (i % width, i / width);
+            assert!(x1 <= x && x <= x2 && y1 <= y && y <= y2);
+        }
+        // Each box is tight: its edges touch a pixel of the region
+        for (label, &[x1, y1, x2, y2]) in bounds.iter().enumerate() {
+            let pixels: Vec<(usize, usize)> =
+                (0..width * height).filter(|&i| segmentation.labels[i] == label as u32).map(|i| (i % width, i / width)).collect();
+            assert!(pixels.iter().any(|p| p.0 == x1) && pixels.iter().any(|p| p.0 == x2));
+            assert!(pixels.iter().any(|p| p.1 == y1) && pixels.iter().any(|p| p.1 == y2));
+        }
+    }
+}
@@ -44,6 +44,95 @@ pub enum DitheringAlgorithmType {
     // Add others as needed, ensure JS side sends corresponding integer
 }
 
+// Parameters for one run of an algorithm; each algorithm ignores the ones it does not use
+#[derive(Clone, Copy, Debug)]
+pub struct AlgorithmParams {
+    pub threshold: u8,
+    pub dot_size: usize,
+    pub spacing: usize,
+    pub angle: f32,
+    pub noise_amount: f32,
+    pub pattern_type: PatternType,
+    pub pattern_size: usize,
+    pub multi_tone_levels: usize,
+    pub multi_tone_algo: MultiToneAlgorithm,
+}
+
+// Defaults match the editor's initial settings
+impl Default for AlgorithmParams {
+    fn default() -> Self {
+        AlgorithmParams {
+            threshold: 128,
+            dot_size: 3,
+            spacing: 5,
+            angle: 45.0,
+            noise_amount: 50.0,
+            pattern_type: PatternType::Dots,
+            pattern_size: 4,
+            multi_tone_levels: 4,
+            multi_tone_algo: MultiToneAlgorithm::Ordered,
+        }
+    }
+}
+
+// Bytes per pixel written by each algorithm: the original four write grayscale, the rest RGBA
+fn output_stride(algo_type: DitheringAlgorithmType) -> usize {
+    match algo_type {
+        DitheringAlgorithmType::Ordered
+        | DitheringAlgorithmType::FloydSteinberg
+        | DitheringAlgorithmType::Atkinson
+        | DitheringAlgorithmType::Halftone => 1,
+        _ => 4,
+    }
+}
+
+// Copy pixel p_idx of an algorithm's output (grayscale or RGBA, see output_stride)
+// into the RGBA output as an opaque pixel
+fn copy_region_pixel(output: &mut [u8], region: &[u8], p_idx: usize, stride: usize) {
+    let pixel = &mut output[p_idx * 4..p_idx * 4 + 4];
+    if stride == 1 {
+        pixel[..3].fill(region[p_idx]);
+    } else {
+        pixel[..3].copy_from_slice(&region[p_idx * 4..p_idx * 4 + 3]);
+    }
+    pixel[3] = 255;
+}
+
+// Run any algorithm on a grayscale plane and return one byte per pixel
+pub fn dither_plane(
+    grayscale: &[u8],
+    width: usize,
+    height: usize,
+    algo_type: DitheringAlgorithmType,
+    params: &AlgorithmParams,
+) -> Vec<u8> {
+    let size = width * height;
+    let mut buffer = vec![0u8; size * 4];
+    run_specific_dither(
+        grayscale.as_ptr(),
+        width, height,
+        algo_type,
+        params.threshold,
+        params.dot_size,
+        params.spacing,
+        params.angle,
+        params.noise_amount,
+        params.pattern_type,
+        params.pattern_size,
+        params.multi_tone_levels,
+        params.multi_tone_algo,
+        buffer.as_mut_ptr(),
+    );
+
+    let stride = output_stride(algo_type);
+    if stride == 1 {
+        buffer.truncate(size);
+        buffer
+    } else {
+        buffer.iter().step_by(stride).copied().collect()
+    }
+}
+
 // Helper function to run a specific dither algorithm on the full grayscale data
 // and write the RGBA result to the provided temporary output buffer.
 fn run_specific_dither(
@@ -194,9 +283,6 @@ pub fn selective_dither(
         let pattern_type: PatternType = unsafe { std::mem::transmute(pattern_type_u32) };
         let mt_algo_u32 = mt_algos_slice[i];
         let mt_algo: MultiToneAlgorithm = unsafe { std::mem::transmute(mt_algo_u32) };
-        
-
-        println!("Processing region {} with algorithm {:?}", i, algo_type);
 
         // Run the chosen algorithm on the *full* image, store in temp buffer
         run_specific_dither(
@@ -220,19 +306,13 @@ pub fn selective_dither(
             // Mask value > 0 means apply this region's result
             // We only apply if not already processed by a previous region (first mask wins)
             if mask_slice[p_idx] > 0 && processed_map[p_idx] == 0 {
-                let base_output_idx = p_idx * 4;
-                let base_region_idx = p_idx * 4;
-                output_slice[base_output_idx] = region_output_buffer[base_region_idx];         // R
-                output_slice[base_output_idx + 1] = region_output_buffer[base_region_idx + 1]; // G
-                output_slice[base_output_idx + 2] = region_output_buffer[base_region_idx + 2]; // B
-                output_slice[base_output_idx + 3] = 255;                                        // A
+                copy_region_pixel(output_slice, &region_output_buffer, p_idx, output_stride(algo_type));
                 processed_map[p_idx] = 1; // Mark as processed
             }
         }
     }
 
     // --- Process Default Region (Unprocessed Pixels) --- 
-    println!("Processing default region with algorithm {:?}", default_algorithm_type);
 
     // Run the default algorithm on the *full* image, store in temp buffer
     run_specific_dither(
@@ -254,13 +334,7 @@ pub fn selective_dither(
     // Merge the default result for any remaining unprocessed pixels
     for p_idx in 0..size {
         if processed_map[p_idx] == 0 { // If not processed by any region mask
-            let base_output_idx = p_idx * 4;
-            let base_region_idx = p_idx * 4;
-            output_slice[base_output_idx] = region_output_buffer[base_region_idx];         // R
-            output_slice[base_output_idx + 1] = region_output_buffer[base_region_idx + 1]; // G
-            output_slice[base_output_idx + 2] = region_output_buffer[base_region_idx + 2]; // B
-            output_slice[base_output_idx + 3] = 255;                                        // A
+            copy_region_pixel(output_slice, &region_output_buffer, p_idx, output_stride(default_algorithm_type));
         }
     }
-     println!("Selective dithering complete.");
 } 
\ No newline at end of file
@@ -0,0 +1,257 @@
+use wasm_bindgen::prelude::*;
+
+// Clustered-dot threshold arrays built from supercells at rational angles
+//
+// A screen at an arbitrary angle only tiles the pixel grid when the cell
+// vector has rational components. Like PostScript screens, we approximate the
+// requested ruling and angle with a cell vector (a, b) / m where a, b and m are
+// integers. The lattice spanned by (a, b) / m and (-b, a) / m then repeats
+// every T pixels in both x and y, and one T x T supercell holds many halftone
+// cells. Ranking all pixels of the supercell by the spot function gives a
+// threshold array that the ordered-dither path can tile without seams.
+
+// Largest supercell we are willing to build (T x T pixels)
+const MAX_SUPERCELL_SIZE: usize = 256;
+
+// Largest cell subdivision tried when searching for a rational angle
+const MAX_CELL_SUBDIVISION: i64 = 16;
+
+// Spot function shapes (PostScript naming)
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotShape {
+    Round,
+    Euclidean,
+    Square,
+    Diamond,
+    Ellipse,
+    Line,
+}
+
+// A tileable threshold array
+#[derive(Clone, Debug)]
+pub struct ThresholdArray {
+    pub size: usize,     // The array is size x size pixels
+    pub values: Vec<u8>, // Row-major thresholds; a pixel is white when tone > threshold
+}
+
+// The integer screen chosen for a requested ruling and angle
+#[derive(Clone, Copy, Debug)]
+struct RationalScreen {
+    a: i64,
+    b: i64,
+    m: i64,
+    size: usize,
+}
+
+fn gcd(mut a: i64, mut b: i64) -> i64 {
+    a = a.abs();
+    b = b.abs();
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+// Supercell size for cell vector (a, b) / m: the smallest T with (T, 0) on the lattice
+fn supercell_size(a: i64, b: i64, m: i64) -> i64 {
+    let norm = a * a + b * b;
+    let period = norm / gcd(a, b);
+    period / gcd(m, period)
+}
+
+// Search integer cell vectors close to the requested cell size and angle whose
+// supercell fits in MAX_SUPERCELL_SIZE
+fn search_rational_screen(cell_size: f64, angle_rad: f64) -> Option<RationalScreen> {
+    let mut best: Option<(f64, RationalScreen)> = None;
+
+    for m in 1..=MAX_CELL_SUBDIVISION {
+        let ideal_a = m as f64 * cell_size * angle_rad.cos();
+        let ideal_b = m as f64 * cell_size * angle_rad.sin();
+
+        // Try the integer points around the ideal cell vector
+        for da in -1..=1 {
+            for db in -1..=1 {
+                let a = ideal_a.round() as i64 + da;
+                let b = ideal_b.round() as i64 + db;
+                if a == 0 && b == 0 { continue; }
+
+                let size = supercell_size(a, b, m);
+                if size as usize > MAX_SUPERCELL_SIZE { continue; }
+
+                // Relative ruling error plus angle error (one degree ~ one percent)
+                let achieved_size = ((a * a + b * b) as f64).sqrt() / m as f64;
+                let achieved_angle = (b as f64).atan2(a as f64);
+                let mut angle_error = (achieved_angle - angle_rad).abs() % std::f64::consts::TAU;
+                angle_error = angle_error.min(std::f64::consts::TAU - angle_error);
+                let score = (achieved_size - cell_size).abs() / cell_size
+                    + angle_error.to_degrees() * 0.01;
+
+                let candidate = RationalScreen { a, b, m, size: size as usize };
+                let better = match best {
+                    None => true,
+                    Some((best_score, best_screen)) => {
+                        score < best_score - 1e-9
+                            || ((score - best_score).abs() <= 1e-9 && candidate.size < best_screen.size)
+                    }
+                };
+                if better {
+                    best = Some((score, candidate));
+                }
+            }
+        }
+    }
+
+    best.map(|(_, screen)| screen)
+}
+
+// Rational screen for the requested ruling and angle. Cells too large for any
+// supercell within the limit (e.g. 300 pixels per line) are shrunk step by step
+// until one fits, so they stay as large as allowed instead of degrading to a
+// plain threshold.
+fn find_rational_screen(cell_size: f32, angle: f32) -> RationalScreen {
+    let angle_rad = (angle as f64).to_radians();
+    let mut cell_size = (cell_size as f64).max(1.0);
+    loop {
+        if let Some(screen) = search_rational_screen(cell_size, angle_rad) {
+            return screen;
+        }
+        // At one pixel per cell the unit vectors are among the candidates and always fit
+        if cell_size <= 1.0 {
+            return RationalScreen { a: 1, b: 0, m: 1, size: 1 };
+        }
+        cell_size = (cell_size * 0.98).max(1.0);
+    }
+}
+
+// Spot function: higher values are inked first. x and y are in [-1, 1].
+fn spot_value(shape: DotShape, x: f64, y: f64) -> f64 {
+    let ax = x.abs();
+    let ay = y.abs();
+    match shape {
+        DotShape::Round => 1.0 - (x * x + y * y),
+        DotShape::Euclidean => {
+            if ax + ay <= 1.0 {
+                1.0 - (x * x + y * y)
+            } else {
+                (ax - 1.0) * (ax - 1.0) + (ay - 1.0) * (ay - 1.0) - 1.0
+            }
+        }
+        DotShape::Square => 1.0 - ax.max(ay),
+        DotShape::Diamond => 1.0 - (ax + ay),
+        DotShape::Ellipse => 1.0 - (x * x + (y / 0.7) * (y / 0.7)),
+        DotShape::Line => 1.0 - ay,
+    }
+}
+
+// 8x8 Bayer index, used to spread ties between cells so gray levels grow evenly
+fn bayer_index(x: i64, y: i64) -> u32 {
+    let x = x.rem_euclid(8) as u32;
+    let y = y.rem_euclid(8) as u32;
+    let xor = x ^ y;
+    let mut value = 0;
+    for bit in 0..3 {
+        value |= ((xor >> bit) & 1) << (5 - 2 * bit);
+        value |= ((y >> bit) & 1) << (4 - 2 * bit);
+    }
+    value
+}
+
+// Build the threshold array for a ruling (lpi), angle (degrees) and device resolution (dpi)
+pub fn generate_threshold_array(lpi: f32, angle: f32, dpi: f32, shape: DotShape) -> ThresholdArray {
+    let cell_size = if lpi > 0.0 { dpi / lpi } else { 1.0 };
+    let screen = find_rational_screen(cell_size, angle);
+    let size = screen.size;
+    let norm = (screen.a * screen.a + screen.b * screen.b) as f64;
+    let m = screen.m as f64;
+
+    // Spot value and tie-break key for every pixel of the supercell
+    let mut keys: Vec<(f64, u32, usize)> = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let px = x as f64 + 0.5;
+            let py = y as f64 + 0.5;
+
+            // Position in cell units along the two screen axes
+            let u = (px * screen.a as f64 + py * screen.b as f64) * m / norm;
+            let v = (-px * screen.b as f64 + py * screen.a as f64) * m / norm;
+            let cell_u = u.floor();
+            let cell_v = v.floor();
+            let spot = spot_value(shape, (u - cell_u) * 2.0 - 1.0, (v - cell_v) * 2.0 - 1.0);
+
+            keys.push((spot, bayer_index(cell_u as i64, cell_v as i64), y * size + x));
+        }
+    }
+
+    // Rank ascending: pixels with the highest spot value get the highest thresholds
+    // and therefore stay dark longest as the tone brightens
+    keys.sort_by(|p, q| p.0.total_cmp(&q.0).then(q.1.cmp(&p.1)));
+
+    let total = keys.len();
+    let mut values = vec![0u8; total];
+    for (rank, &(_, _, idx)) in keys.iter().enumerate() {
+        values[idx] = (rank * 255 / total) as u8;
+    }
+
+    ThresholdArray { size, values }
+}
+
+// Size of the supercell that generate_threshold_array would build
+pub fn threshold_array_size(lpi: f32, angle: f32, dpi: f32) -> usize {
+    let cell_size = if lpi > 0.0 { dpi / lpi } else { 1.0 };
+    find_rational_screen(cell_size, angle).size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_SHAPES: [DotShape; 6] =
+        [DotShape::Round, DotShape::Euclidean, DotShape::Square, DotShape::Diamond, DotShape::Ellipse, DotShape::Line];
+
+    // (lpi, angle, dpi) covering the usual CMYK angles and an oversized cell
+    const SCREENS: [(f32, f32, f32); 6] =
+        [(60.0, 45.0, 300.0), (85.0, 15.0, 600.0), (50.0, 75.0, 300.0), (100.0, 0.0, 300.0), (45.0, 108.4, 300.0), (1.0, 30.0, 300.0)];
+
+    #[test]
+    fn threshold_arrays_are_a_permutation_of_the_ranks() {
+        for (lpi, angle, dpi) in SCREENS {
+            for shape in ALL_SHAPES {
+                let array = generate_threshold_array(lpi, angle, dpi, shape);
+                let total = array.size * array.size;
+                assert_eq!(array.values.len(), total);
+                assert_eq!(array.size, threshold_array_size(lpi, angle, dpi));
+                let mut values = array.values.clone();
+                values.sort_unstable();
+                let ranks: Vec<u8> = (0..total).map(|rank| (rank * 255 / total) as u8).collect();
+                assert_eq!(values, ranks, "{} lpi at {} degrees, {:?}", lpi, angle, shape);
+            }
+        }
+    }
+
+    #[test]
+    fn supercells_repeat_the_screen_lattice() {
+        for (lpi, angle, dpi) in SCREENS {
+            let screen = find_rational_screen(dpi / lpi, angle);
+            let (t, norm) = (screen.size as i64, screen.a * screen.a + screen.b * screen.b);
+            assert!(screen.size <= MAX_SUPERCELL_SIZE);
+            // (T, 0) and (0, T) have integer coordinates in the basis (a, b) / m, (-b, a) / m
+            assert_eq!((t * screen.m * screen.a) % norm, 0, "{:?}", screen);
+            assert_eq!((t * screen.m * screen.b) % norm, 0, "{:?}", screen);
+        }
+    }
+
+    #[test]
+    fn rational_screens_stay_close_to_the_request() {
+        for (lpi, angle, dpi) in SCREENS.into_iter().filter(|&(lpi, _, _)| lpi > 1.0) {
+            let screen = find_rational_screen(dpi / lpi, angle);
+            let achieved_size = ((screen.a * screen.a + screen.b * screen.b) as f32).sqrt() / screen.m as f32;
+            let achieved_angle = (screen.b as f32).atan2(screen.a as f32).to_degrees();
+            assert!((achieved_size - dpi / lpi).abs() / (dpi / lpi) < 0.03, "{:?} for {} lpi", screen, lpi);
+            assert!((achieved_angle - angle).abs() < 1.0, "{:?} for {} degrees", screen, angle);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use wasm_bindgen::prelude::*;
+use std::fmt::Write;
+
+use crate::vector::{svg_header, SvgOptions, SvgUnit};
+
+// Binary raster to compact SVG
+//
+// Dark pixels of a one-byte-per-pixel dithered image are turned into SVG
+// without one element per pixel. Horizontal runs become rects, identical runs
+// on consecutive rows are merged into taller rects, or each 4-connected region
+// is traced into a single path whose outline follows the pixel edges. All three
+// are lossless; despeckling small regions trades fidelity for size.
+
+// How dark pixels are grouped into SVG elements
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgTraceMode {
+    Runs,  // One rect per horizontal run
+    Rects, // Runs repeated on consecutive rows merged into one rect
+    Paths, // One path per connected region, holes included
+}
+
+// Result of a trace: the SVG document and how many shape elements it holds
+#[wasm_bindgen]
+pub struct SvgTrace {
+    svg: String,
+    element_count: usize,
+}
+
+#[wasm_bindgen]
+impl SvgTrace {
+    #[wasm_bindgen(getter)]
+    pub fn svg(&self) -> String {
+        self.svg.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn element_count(&self) -> usize {
+        self.element_count
+    }
+}
+
+// Label 4-connected regions of set pixels; returns labels (0 = background) and region sizes
+fn label_regions(on: &[bool], width: usize, height: usize) -> (Vec<u32>, Vec<usize>) {
+    let mut labels = vec![0u32; on.len()];
+    let mut sizes = vec![0usize]; // Index 0 is the background
+    let mut stack = Vec::new();
+
+    for start in 0..on.len() {
+        if !on[start] || labels[start] != 0 { continue; }
+        let label = sizes.len() as u32;
+        let mut size = 0;
+        labels[start] = label;
+        stack.push(start);
+
+        while let Some(idx) = stack.pop() {
+            size += 1;
+            let x = idx % width;
+            let y = idx / width;
+            let mut visit = |n: usize| {
+                if on[n] && labels[n] == 0 {
+                    labels[n] = label;
+                    stack.push(n);
+                }
+            };
+            if x > 0 { visit(idx - 1); }
+            if x + 1 < width { visit(idx + 1); }
+            if y > 0 { visit(idx - width); }
+            if y + 1 < height { visit(idx + width); }
+        }
+        sizes.push(size);
+    }
+    (labels, sizes)
+}
+
+// Horizontal runs of set pixels in one row as (start, end) with end exclusive
+fn row_runs(on: &[bool], width: usize, y: usize) -> Vec<(usize, usize)> {
+    let row = &on[y * width..(y + 1) * width];
+    let mut runs = Vec::new();
+    let mut x = 0;
+    while x < width {
+        if row[x] {
+            let start = x;
+            while x < width && row[x] {
+                x += 1;
+            }
+            runs.push((start, x));
+        } else {
+            x += 1;
+        }
+    }
+    runs
+}
+
+fn push_rect(svg: &mut String, x: usize, y: usize, width: usize, height: usize) {
+    let _ = write!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>", x, y, width, height);
+}
+
+// One rect per run
+fn trace_runs(on: &[bool], width: usize, height: usize, svg: &mut String) -> usize {
+    let mut count = 0;
+    for y in 0..height {
+        for (start, end) in row_runs(on, width, y) {
+            push_rect(svg, start, y, end - start, 1);
+            count += 1;
+        }
+    }
+    count
+}
+
+// Runs that repeat exactly on the next row grow the same rect downwards
+fn trace_rects(on: &[bool], width: usize, height: usize, svg: &mut String) -> usize {
+    let mut count = 0;
+    // Open rects: (start, end, first row)
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+
+    for y in 0..=height {
+        let runs = if y < height { row_runs(on, width, y) } else { Vec::new() };
+        let mut next_open = Vec::with_capacity(runs.len());
+        for &(start, end) in &runs {
+            match open.iter().position(|&(s, e, _)| s == start && e == end) {
+                Some(pos) => next_open.push(open.swap_remove(pos)),
+                None => next_open.push((start, end, y)),
+            }
+        }
+        // Whatever did not continue ends on the previous row
+        for (start, end, top) in open {
+            push_rect(svg, start, top, end - start, y - top);
+            count += 1;
+        }
+        open = next_open;
+    }
+    count
+}
+
+// Outline every region along pixel edges; holes come out as inner loops (evenodd)
+fn trace_paths(on: &[bool], width: usize, height: usize, svg: &mut String) -> usize {
+    let (labels, sizes) = label_regions(on, width, height);
+    let vertex = |x: usize, y: usize| (y * (width + 1) + x) as u64;
+
+    // Directed boundary edges grouped by region, clockwise around each set pixel
+    let mut edges: Vec<Vec<(u64, u64)>> = vec![Vec::new(); sizes.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            if !on[idx] { continue; }
+            let region = &mut edges[labels[idx] as usize];
+            if y == 0 || !on[idx - width] { region.push((vertex(x, y), vertex(x + 1, y))); }
+            if x + 1 == width || !on[idx + 1] { region.push((vertex(x + 1, y), vertex(x + 1, y + 1))); }
+            if y + 1 == height || !on[idx + width] { region.push((vertex(x + 1, y + 1), vertex(x, y + 1))); }
+            if x == 0 || !on[idx - 1] { region.push((vertex(x, y + 1), vertex(x, y))); }
+        }
+    }
+
+    let mut count = 0;
+    for mut region in edges.into_iter().skip(1) {
+        if region.is_empty() { continue; }
+        region.sort_unstable();
+        let mut used = vec![false; region.len()];
+        let mut d = String::new();
+
+        for first in 0..region.len() {
+            if used[first] { continue; }
+
+            // Walk edges until the loop closes, collecting the corner points
+            let loop_start = region[first].0;
+            let mut points = vec![loop_start];
+            let mut current = first;
+            loop {
+                used[current] = true;
+                let end = region[current].1;
+                points.push(end);
+                if end == loop_start { break; }
+                // Any unused edge leaving this vertex keeps the edge set intact,
+                // which is all the evenodd fill depends on
+                let from = region.partition_point(|e| e.0 < end);
+                match (from..region.len()).take_while(|&i| region[i].0 == end).find(|&i| !used[i]) {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+
+            // Keep only corners: drop points in the middle of straight stretches
+            let coords: Vec<(usize, usize)> = points
+                .iter()
+                .map(|&v| ((v % (width as u64 + 1)) as usize, (v / (width as u64 + 1)) as usize))
+                .collect();
+            let _ = write!(d, "M{} {}", coords[0].0, coords[0].1);
+            for i in 1..coords.len() - 1 {
+                let (px, py) = coords[i - 1];
+                let (cx, cy) = coords[i];
+                let (nx, ny) = coords[i + 1];
+                let straight = (px == cx && cx == nx) || (py == cy && cy == ny);
+                if straight { continue; }
+                if cx == px {
+                    let _ = write!(d, "V{}", cy);
+                } else {
+                    let _ = write!(d, "H{}", cx);
+                }
+            }
+            d.push('Z');
+        }
+
+        let _ = write!(svg, "<path d=\"{}\"/>", d);
+        count += 1;
+    }
+    count
+}
+
+// Trace the dark pixels (< 128) of a binary plane into SVG. Regions smaller than
+// `despeckle_area` pixels are dropped first (0 keeps the output lossless).
+pub fn trace_binary(
+    plane: &[u8],
+    width: usize,
+    height: usize,
+    mode: SvgTraceMode,
+    despeckle_area: usize,
+    options: &SvgOptions,
+) -> SvgTrace {
+    let mut on: Vec<bool> = plane.iter().map(|&v| v < 128).collect();
+
+    if despeckle_area > 0 {
+        let (labels, sizes) = label_regions(&on, width, height);
+        for (pixel, &label) in on.iter_mut().zip(labels.iter()) {
+            if label != 0 && sizes[label as usize] < despeckle_area {
+                *pixel = false;
+            }
+        }
+    }
+
+    let mut svg = svg_header(width, height, options);
+    let _ = write!(svg, "<g fill=\"{}\"{}>", options.foreground,
+        if mode == SvgTraceMode::Paths { " fill-rule=\"evenodd\"" } else { "" });
+    let element_count = match mode {
+        SvgTraceMode::Runs => trace_runs(&on, width, height, &mut svg),
+        SvgTraceMode::Rects => trace_rects(&on, width, height, &mut svg),
+        SvgTraceMode::Paths => trace_paths(&on, width, height, &mut svg),
+    };
+    svg.push_str("</g></svg>");
+
+    SvgTrace { svg, element_count }
+}
+
+// Trace a one-byte-per-pixel dithered image into SVG
+#[allow(clippy::too_many_arguments)]
+pub fn binary_to_svg(
+    input_ptr: *const u8,
+    width: usize,
+    height: usize,
+    mode: SvgTraceMode,
+    despeckle_area: usize,
+    unit: SvgUnit,
+    dpi: f32,
+    include_background: bool,
+) -> SvgTrace {
+    let input = unsafe { std::slice::from_raw_parts(input_ptr, width * height) };
+    let options = SvgOptions {
+        unit,
+        dpi,
+        foreground: "#000000".to_string(),
+        background: if include_background { Some("#ffffff".to_string()) } else { None },
+    };
+    trace_binary(input, width, height, mode, despeckle_area, &options)
+}
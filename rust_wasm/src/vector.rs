@@ -0,0 +1,196 @@
+use wasm_bindgen::prelude::*;
+use std::fmt::Write;
+
+// Vector dot geometry and SVG serialization
+//
+// The halftone and pattern modules describe their output as a list of shapes
+// (center, half extents, rotation) instead of pixels. Serializing that list
+// gives an SVG whose shapes cover exactly the pixel centers the raster path
+// turns on, without tracing the raster afterwards.
+
+// Shape of one vector dot
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorShape {
+    Circle,        // rx = radius
+    ClippedCircle, // Circle of radius rx cut by a square of half side ry
+    Square,        // rx = ry = half side
+    Diamond,       // rx = ry = half diagonal
+    Cross,         // Two bars: rx = bar half width, ry = half length
+    Rect,          // rx = half width, ry = half height
+}
+
+// One dot, in pixel coordinates of the source image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VectorDot {
+    pub shape: VectorShape,
+    pub cx: f32,
+    pub cy: f32,
+    pub rx: f32,
+    pub ry: f32,
+    pub rotation: f32, // Degrees, clockwise around (cx, cy)
+}
+
+// Number of floats per dot in the flat geometry arrays returned to JS
+pub const FLOATS_PER_DOT: usize = 6;
+
+// Physical unit for the SVG width and height attributes
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgUnit {
+    Px,
+    Mm,
+    In,
+    Pt,
+}
+
+// SVG output settings
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    pub unit: SvgUnit,
+    pub dpi: f32, // Image pixels per inch, used for physical units
+    pub foreground: String,
+    pub background: Option<String>,
+}
+
+impl SvgOptions {
+    // Colors of the raster preview: white (255) shapes on a black (0) background
+    pub fn matching_raster(unit: SvgUnit, dpi: f32, include_background: bool) -> Self {
+        SvgOptions {
+            unit,
+            dpi,
+            foreground: "#ffffff".to_string(),
+            background: if include_background { Some("#000000".to_string()) } else { None },
+        }
+    }
+}
+
+// Format a coordinate with at most three decimals and no trailing zeros
+pub fn format_number(value: f32) -> String {
+    let mut text = format!("{:.3}", value);
+    if text.contains('.') {
+        while text.ends_with('0') {
+            text.pop();
+        }
+        if text.ends_with('.') {
+            text.pop();
+        }
+    }
+    if text == "-0" { "0".to_string() } else { text }
+}
+
+// Length in the requested unit for a number of image pixels
+fn physical_length(pixels: usize, options: &SvgOptions) -> String {
+    let inches = pixels as f32 / options.dpi.max(1e-3);
+    match options.unit {
+        SvgUnit::Px => format!("{}", pixels),
+        SvgUnit::Mm => format!("{}mm", format_number(inches * 25.4)),
+        SvgUnit::In => format!("{}in", format_number(inches)),
+        SvgUnit::Pt => format!("{}pt", format_number(inches * 72.0)),
+    }
+}
+
+// Opening <svg> tag (and background) shared by all SVG exporters
+pub fn svg_header(width: usize, height: usize, options: &SvgOptions) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        physical_length(width, options),
+        physical_length(height, options),
+        width,
+        height
+    );
+    if let Some(background) = &options.background {
+        let _ = write!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>", width, height, background);
+    }
+    svg
+}
+
+// Rotation attribute, omitted for unrotated shapes
+fn rotation_attribute(dot: &VectorDot) -> String {
+    if dot.rotation == 0.0 {
+        String::new()
+    } else {
+        format!(
+            " transform=\"rotate({} {} {})\"",
+            format_number(dot.rotation),
+            format_number(dot.cx),
+            format_number(dot.cy)
+        )
+    }
+}
+
+// Path for a circle of radius r cut by the square [-h, h]^2 around (cx, cy)
+fn clipped_circle_path(dot: &VectorDot) -> String {
+    let r = dot.rx;
+    let h = dot.ry;
+    let a = (r * r - h * h).max(0.0).sqrt();
+    let n = format_number;
+    let (cx, cy) = (dot.cx, dot.cy);
+
+    // Straight along each side of the square, circular arcs around the corners
+    format!(
+        "M{} {}L{} {}A{r} {r} 0 0 1 {} {}L{} {}A{r} {r} 0 0 1 {} {}L{} {}A{r} {r} 0 0 1 {} {}L{} {}A{r} {r} 0 0 1 {} {}Z",
+        n(cx + h), n(cy - a), n(cx + h), n(cy + a),
+        n(cx + a), n(cy + h), n(cx - a), n(cy + h),
+        n(cx - h), n(cy + a), n(cx - h), n(cy - a),
+        n(cx - a), n(cy - h), n(cx + a), n(cy - h),
+        n(cx + h), n(cy - a),
+        r = n(r),
+    )
+}
+
+// Serialize one dot as an SVG element
+fn dot_element(dot: &VectorDot) -> String {
+    let n = format_number;
+    let transform = rotation_attribute(dot);
+    match dot.shape {
+        VectorShape::Circle => format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\"/>", n(dot.cx), n(dot.cy), n(dot.rx)),
+        VectorShape::ClippedCircle => format!("<path d=\"{}\"{}/>", clipped_circle_path(dot), transform),
+        VectorShape::Square | VectorShape::Rect => format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}/>",
+            n(dot.cx - dot.rx),
+            n(dot.cy - dot.ry),
+            n(dot.rx * 2.0),
+            n(dot.ry * 2.0),
+            transform
+        ),
+        VectorShape::Diamond => format!(
+            "<polygon points=\"{},{} {},{} {},{} {},{}\"{}/>",
+            n(dot.cx), n(dot.cy - dot.ry),
+            n(dot.cx + dot.rx), n(dot.cy),
+            n(dot.cx), n(dot.cy + dot.ry),
+            n(dot.cx - dot.rx), n(dot.cy),
+            transform
+        ),
+        VectorShape::Cross => format!(
+            "<path d=\"M{} {}h{}v{}h{}ZM{} {}h{}v{}h{}Z\"{}/>",
+            // Vertical bar
+            n(dot.cx - dot.rx), n(dot.cy - dot.ry), n(dot.rx * 2.0), n(dot.ry * 2.0), n(-dot.rx * 2.0),
+            // Horizontal bar
+            n(dot.cx - dot.ry), n(dot.cy - dot.rx), n(dot.ry * 2.0), n(dot.rx * 2.0), n(-dot.ry * 2.0),
+            transform
+        ),
+    }
+}
+
+// Serialize dots to a standalone SVG document
+pub fn dots_to_svg(dots: &[VectorDot], width: usize, height: usize, options: &SvgOptions) -> String {
+    let mut svg = svg_header(width, height, options);
+    let _ = write!(svg, "<g fill=\"{}\">", options.foreground);
+    for dot in dots {
+        svg.push_str(&dot_element(dot));
+    }
+    svg.push_str("</g></svg>");
+    svg
+}
+
+// Flatten dots to [shape, cx, cy, rx, ry, rotation] per dot for JS
+pub fn flatten_dots(dots: &[VectorDot]) -> Vec<f32> {
+    let mut flat = Vec::with_capacity(dots.len() * FLOATS_PER_DOT);
+    for dot in dots {
+        flat.extend_from_slice(&[dot.shape as u32 as f32, dot.cx, dot.cy, dot.rx, dot.ry, dot.rotation]);
+    }
+    flat
+}
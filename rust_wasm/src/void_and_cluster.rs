@@ -1,179 +1,311 @@
-use rand::Rng;
-use std::f32::consts::E;
-
-// Initialize a random binary pattern (0 or 1) with approximately percent_ones density
-fn initialize_random_pattern(width: usize, height: usize, percent_ones: f32) -> Vec<Vec<u8>> {
-    let mut pattern = vec![vec![0u8; width]; height];
-    let num_ones = (width as f32 * height as f32 * percent_ones).floor() as usize;
-    let mut rng = rand::thread_rng();
-    let mut ones_placed = 0;
-
-    while ones_placed < num_ones {
-        let x = rng.gen_range(0..width);
-        let y = rng.gen_range(0..height);
-        if pattern[y][x] == 0 {
-            pattern[y][x] = 1;
-            ones_placed += 1;
-        }
-    }
-    pattern
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use crate::blue_noise::mask_dither;
+
+// Void-and-cluster threshold masks
+//
+// Ulichney's method: a sparse random pattern is relaxed until no cluster is
+// tighter than the largest void, then every pixel gets a rank by repeatedly
+// removing the tightest cluster (ranks below the initial count) or filling the
+// largest void (ranks above it). Tightness is the Gaussian-weighted count of
+// set pixels around a position (its energy) on a torus, so masks tile.
+//
+// Toggling a pixel only changes the energy inside the kernel footprint, so the
+// energy map is updated in place, and two selection trees keep the tightest
+// cluster and the largest void ready in O(log N). Generation is roughly
+// N * footprint instead of a full rescan per rank, so 256x256 masks are fine.
+
+// Mask size used by the dither functions
+pub const DEFAULT_MATRIX_SIZE: usize = 64;
+// Width of the Gaussian energy filter
+const SIGMA: f32 = 1.5;
+// Fraction of pixels set in the initial pattern
+const INITIAL_DENSITY: f32 = 0.1;
+
+// Most masks a thread keeps; a UI that reseeds on every change would otherwise
+// grow memory by size^2 floats per seed
+const MASK_CACHE_CAPACITY: usize = 4;
+
+// Recently generated masks by (size, seed), least recently used first
+type MaskCache = VecDeque<((usize, u64), Rc<Vec<f32>>)>;
+
+thread_local! {
+    static MASK_CACHE: RefCell<MaskCache> = const { RefCell::new(VecDeque::new()) };
+}
+
+// Binary tree over per-pixel keys whose root is the best (largest or smallest) key.
+// Pixels that must not be picked hold -inf (maximizing) or +inf (minimizing).
+struct SelectionTree {
+    leaves: usize,
+    maximize: bool,
+    keys: Vec<f32>,  // Best key in each subtree, root at 1
+    winner: Vec<u32>, // Pixel holding that key
 }
 
-// Calculate the cluster value for a pixel using Gaussian blur (toroidal)
-fn calculate_cluster_value(
-    pattern: &[Vec<u8>],
-    x: usize, y: usize,
-    width: usize, height: usize,
-    radius: isize,
-    gaussian_weights: &[Vec<f32>]
-) -> f32 {
-    let mut sum = 0.0;
-    let radius_f = radius as f32;
-
-    for dy in -radius..=radius {
-        for dx in -radius..=radius {
-            if dx == 0 && dy == 0 { continue; }
-
-            let nx = (x as isize + dx + width as isize) as usize % width; // Wrap around X
-            let ny = (y as isize + dy + height as isize) as usize % height; // Wrap around Y
-
-            // Use precalculated weight
-            let weight = gaussian_weights[(dy + radius) as usize][(dx + radius) as usize];
-            sum += pattern[ny][nx] as f32 * weight;
+impl SelectionTree {
+    fn new(count: usize, maximize: bool) -> Self {
+        let leaves = count.next_power_of_two();
+        let excluded = if maximize { f32::NEG_INFINITY } else { f32::INFINITY };
+        let mut winner = vec![0u32; 2 * leaves];
+        for i in 0..leaves {
+            winner[leaves + i] = i as u32;
+        }
+        let mut tree = SelectionTree { leaves, maximize, keys: vec![excluded; 2 * leaves], winner };
+        for node in (1..leaves).rev() {
+            tree.pull(node);
         }
+        tree
+    }
+
+    fn excluded(&self) -> f32 {
+        if self.maximize { f32::NEG_INFINITY } else { f32::INFINITY }
+    }
+
+    // Recompute a node from its children; ties go to the lower pixel index
+    fn pull(&mut self, node: usize) {
+        let (left, right) = (2 * node, 2 * node + 1);
+        let right_wins = if self.maximize {
+            self.keys[right] > self.keys[left]
+        } else {
+            self.keys[right] < self.keys[left]
+        };
+        let child = if right_wins { right } else { left };
+        self.keys[node] = self.keys[child];
+        self.winner[node] = self.winner[child];
     }
-    sum
-}
 
-// Precalculate Gaussian weights for the cluster calculation
-fn precalculate_gaussian_weights(radius: isize) -> Vec<Vec<f32>> {
-    let diameter = (radius * 2 + 1) as usize;
-    let mut weights = vec![vec![0.0; diameter]; diameter];
-    let radius_f = radius as f32;
-    let variance = radius_f * radius_f; // Simple variance estimate
-
-    for dy in -radius..=radius {
-        for dx in -radius..=radius {
-            if dx == 0 && dy == 0 { continue; }
-            let dist_sq = (dx * dx + dy * dy) as f32;
-            let weight = E.powf(-dist_sq / (2.0 * variance));
-            weights[(dy + radius) as usize][(dx + radius) as usize] = weight;
+    fn set(&mut self, index: usize, key: f32) {
+        self.set_leaf(index, key);
+        self.refresh(index, index);
+    }
+
+    fn exclude(&mut self, index: usize) {
+        self.set(index, self.excluded());
+    }
+
+    // Change a key without updating its ancestors; call refresh afterwards
+    fn set_leaf(&mut self, index: usize, key: f32) {
+        self.keys[self.leaves + index] = key;
+    }
+
+    // Update the ancestors of leaves first..=last, sharing the nodes a run of leaves has in common
+    fn refresh(&mut self, first: usize, last: usize) {
+        let (mut low, mut high) = (self.leaves + first, self.leaves + last);
+        while low > 1 {
+            low /= 2;
+            high /= 2;
+            for node in low..=high {
+                self.pull(node);
+            }
         }
     }
-    weights
+
+    fn best(&self) -> usize {
+        self.winner[1] as usize
+    }
 }
 
+// Binary pattern with its Gaussian energy map and the two selection trees
+struct VoidAndCluster {
+    size: usize,
+    radius: usize,
+    kernel: Vec<f32>, // (2 * radius + 1)^2 weights
+    pattern: Vec<bool>,
+    energy: Vec<f32>,
+    clusters: SelectionTree, // Energy of set pixels
+    voids: SelectionTree,    // Energy of empty pixels
+}
 
-// Find the pixel with the minimum cluster value (largest void)
-fn find_largest_void(
-    pattern: &[Vec<u8>],
-    width: usize, height: usize,
-    radius: isize,
-    gaussian_weights: &[Vec<f32>]
-) -> (usize, usize) {
-    let mut min_cluster_value = f32::MAX;
-    let mut void_pos = (0, 0);
-
-    for y in 0..height {
-        for x in 0..width {
-            if pattern[y][x] == 0 {
-                let cluster_value = calculate_cluster_value(pattern, x, y, width, height, radius, gaussian_weights);
-                if cluster_value < min_cluster_value {
-                    min_cluster_value = cluster_value;
-                    void_pos = (x, y);
-                }
+impl VoidAndCluster {
+    fn new(size: usize) -> Self {
+        // 3 sigma covers the filter; keep the footprint inside one tile so no pixel is counted twice
+        let radius = ((3.0 * SIGMA).ceil() as usize).min((size - 1) / 2);
+        let diameter = 2 * radius + 1;
+        let mut kernel = vec![0.0; diameter * diameter];
+        for dy in 0..diameter {
+            for dx in 0..diameter {
+                let ox = dx as f32 - radius as f32;
+                let oy = dy as f32 - radius as f32;
+                kernel[dy * diameter + dx] = (-(ox * ox + oy * oy) / (2.0 * SIGMA * SIGMA)).exp();
             }
         }
+
+        let total = size * size;
+        let mut state = VoidAndCluster {
+            size,
+            radius,
+            kernel,
+            pattern: vec![false; total],
+            energy: vec![0.0; total],
+            clusters: SelectionTree::new(total, true),
+            voids: SelectionTree::new(total, false),
+        };
+        state.rebuild_trees();
+        state
     }
-    void_pos
-}
 
-// Find the pixel with the maximum cluster value (tightest cluster)
-fn find_tightest_cluster(
-    pattern: &[Vec<u8>],
-    width: usize, height: usize,
-    radius: isize,
-    gaussian_weights: &[Vec<f32>]
-) -> (usize, usize) {
-    let mut max_cluster_value = -f32::MAX;
-    let mut cluster_pos = (0, 0);
-
-    for y in 0..height {
-        for x in 0..width {
-            if pattern[y][x] == 1 {
-                 let cluster_value = calculate_cluster_value(pattern, x, y, width, height, radius, gaussian_weights);
-                if cluster_value > max_cluster_value {
-                    max_cluster_value = cluster_value;
-                    cluster_pos = (x, y);
+    fn rebuild_trees(&mut self) {
+        let total = self.size * self.size;
+        self.clusters = SelectionTree::new(total, true);
+        self.voids = SelectionTree::new(total, false);
+        for i in 0..total {
+            if self.pattern[i] {
+                self.clusters.set(i, self.energy[i]);
+            } else {
+                self.voids.set(i, self.energy[i]);
+            }
+        }
+    }
+
+    // Set or clear one pixel, updating the energy in its footprint (wrapping around the torus)
+    fn toggle(&mut self, index: usize) {
+        let set = !self.pattern[index];
+        self.pattern[index] = set;
+        if set {
+            self.voids.exclude(index);
+        } else {
+            self.clusters.exclude(index);
+        }
+
+        let sign = if set { 1.0 } else { -1.0 };
+        let size = self.size as isize;
+        let radius = self.radius as isize;
+        let diameter = 2 * self.radius + 1;
+        let x = (index % self.size) as isize;
+        let y = (index / self.size) as isize;
+        for dy in -radius..=radius {
+            let row = (y + dy).rem_euclid(size) as usize * self.size;
+            let kernel_row = (dy + radius) as usize * diameter;
+            for dx in -radius..=radius {
+                let neighbor = row + (x + dx).rem_euclid(size) as usize;
+                self.energy[neighbor] += sign * self.kernel[kernel_row + (dx + radius) as usize];
+                if self.pattern[neighbor] {
+                    self.clusters.set_leaf(neighbor, self.energy[neighbor]);
+                } else {
+                    self.voids.set_leaf(neighbor, self.energy[neighbor]);
                 }
             }
+
+            // The footprint row is one run of columns, or two where it wraps around the edge
+            let first = x - radius;
+            let last = x + radius;
+            let runs = if first < 0 {
+                [Some((0, last)), Some((first + size, size - 1))]
+            } else if last >= size {
+                [Some((first, size - 1)), Some((0, last - size))]
+            } else {
+                [Some((first, last)), None]
+            };
+            for (start, end) in runs.into_iter().flatten() {
+                let (start, end) = (row + start as usize, row + end as usize);
+                self.clusters.refresh(start, end);
+                self.voids.refresh(start, end);
+            }
         }
     }
-    cluster_pos
+
+    fn tightest_cluster(&self) -> usize {
+        self.clusters.best()
+    }
+
+    fn largest_void(&self) -> usize {
+        self.voids.best()
+    }
 }
 
-// Generate the Void-and-Cluster dither matrix
-fn generate_void_and_cluster_matrix(matrix_size: usize) -> Vec<Vec<f32>> {
-    if matrix_size == 0 { return vec![]; }
+// Rank of every pixel of a size x size void-and-cluster mask (0 .. size^2 - 1)
+pub fn void_and_cluster_ranks(size: usize, seed: u64) -> Vec<usize> {
+    let size = size.max(1);
+    let total = size * size;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = VoidAndCluster::new(size);
 
-    let mut working_pattern = initialize_random_pattern(matrix_size, matrix_size, 0.5);
-    let mut rank_matrix = vec![vec![0usize; matrix_size]; matrix_size];
-    let total_pixels = matrix_size * matrix_size;
-    let mut binary_count = working_pattern.iter().flatten().filter(|&&p| p == 1).count();
-    let mut rank = 0;
-    let radius = 2; // Gaussian radius
-    let gaussian_weights = precalculate_gaussian_weights(radius);
-
-
-    // Phase 1: Void Identification (Place 1s in largest voids)
-    while binary_count < total_pixels {
-        let (vx, vy) = find_largest_void(&working_pattern, matrix_size, matrix_size, radius, &gaussian_weights);
-        if working_pattern[vy][vx] == 0 { // Ensure we haven't somehow already filled it
-             rank_matrix[vy][vx] = rank;
-             working_pattern[vy][vx] = 1;
-             binary_count += 1;
-             rank += 1;
-        } else {
-            // This case should ideally not happen if logic is correct, 
-            // but break to prevent infinite loop if it does.
-             eprintln!("Warning: Tried to fill an already filled void.");
-            break;
+    // Sparse random starting pattern
+    let initial_count = ((total as f32 * INITIAL_DENSITY) as usize).clamp(1, total);
+    let mut placed = 0;
+    while placed < initial_count {
+        let index = rng.gen_range(0..total);
+        if !state.pattern[index] {
+            state.toggle(index);
+            placed += 1;
         }
     }
 
-    // Reset rank for cluster phase (ranking continues from total_pixels down to 1)
-    rank = total_pixels - 1; // Start ranking clusters from the end
+    // Relax: move the tightest cluster into the largest void until that is a no-op
+    // (bounded, in case float ties make two pixels trade places forever)
+    for _ in 0..total {
+        let cluster = state.tightest_cluster();
+        state.toggle(cluster);
+        let void = state.largest_void();
+        state.toggle(void);
+        if void == cluster { break; }
+    }
+    let initial_pattern = state.pattern.clone();
+    let initial_energy = state.energy.clone();
 
-    // Phase 2: Cluster Identification (Remove 1s from tightest clusters)
-    while binary_count > 0 {
-         let (cx, cy) = find_tightest_cluster(&working_pattern, matrix_size, matrix_size, radius, &gaussian_weights);
-         if working_pattern[cy][cx] == 1 { // Ensure it's a cluster
-            rank_matrix[cy][cx] = rank; // Assign rank
-            working_pattern[cy][cx] = 0; // Remove the cluster point
-            binary_count -= 1;
-            if rank > 0 { // Prevent underflow
-                 rank -= 1;
-            }
-         } else {
-             eprintln!("Warning: Tried to remove a non-existent cluster.");
-             break;
-         }
-    }
-
-    // Normalize the rank matrix to 0.0 - 1.0
-    let total_ranks_f = (total_pixels as f32).max(1.0) - 1.0; // Avoid division by zero
-    let normalized_matrix: Vec<Vec<f32>> = rank_matrix
-        .iter()
-        .map(|row| {
-            row.iter()
-               .map(|&r| r as f32 / total_ranks_f)
-               .collect()
-        })
-        .collect();
-
-    normalized_matrix
+    let mut ranks = vec![0usize; total];
+
+    // Ranks below the initial count: remove tightest clusters, last rank first
+    for rank in (0..initial_count).rev() {
+        let cluster = state.tightest_cluster();
+        state.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+
+    // Ranks from the initial count up: fill largest voids. Past half full this
+    // picks the tightest cluster of empty pixels, as the kernel sums to a constant.
+    state.pattern = initial_pattern;
+    state.energy = initial_energy;
+    state.rebuild_trees();
+    for rank in initial_count..total {
+        let void = state.largest_void();
+        state.toggle(void);
+        ranks[void] = rank;
+    }
+    ranks
+}
+
+// Threshold mask, size x size values in (0, 1), each (rank + 0.5) / size^2.
+// The last few masks are cached, so repeated calls with the same size and seed are free.
+pub fn void_and_cluster_mask(size: usize, seed: u64) -> Rc<Vec<f32>> {
+    let size = size.max(1);
+    let cached = MASK_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let position = cache.iter().position(|(key, _)| *key == (size, seed))?;
+        let entry = cache.remove(position)?;
+        let mask = entry.1.clone();
+        cache.push_back(entry);
+        Some(mask)
+    });
+    if let Some(mask) = cached {
+        return mask;
+    }
+    let total = (size * size) as f32;
+    let mask: Rc<Vec<f32>> = Rc::new(
+        void_and_cluster_ranks(size, seed)
+            .into_iter()
+            .map(|rank| (rank as f32 + 0.5) / total)
+            .collect(),
+    );
+    MASK_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() == MASK_CACHE_CAPACITY {
+            cache.pop_front();
+        }
+        cache.push_back(((size, seed), mask.clone()));
+    });
+    mask
 }
 
+// Generate the Void-and-Cluster dither matrix (rows of thresholds in 0.0 - 1.0)
+pub fn generate_void_and_cluster_matrix(matrix_size: usize) -> Vec<Vec<f32>> {
+    if matrix_size == 0 { return vec![]; }
+    let mask = void_and_cluster_mask(matrix_size, 0);
+    mask.chunks_exact(matrix_size).map(|row| row.to_vec()).collect()
+}
 
 pub fn void_and_cluster_dither(
     input_ptr: *const u8,
@@ -186,45 +318,15 @@ pub fn void_and_cluster_dither(
     let input = unsafe { std::slice::from_raw_parts(input_ptr, size) };
     let output = unsafe { std::slice::from_raw_parts_mut(output_ptr, size * 4) }; // RGBA output
 
-    // Generate or load the dither matrix (using a fixed size for now)
-    let matrix_size = 32;
-    let dither_matrix = generate_void_and_cluster_matrix(matrix_size);
-
-    if dither_matrix.is_empty() || dither_matrix[0].is_empty() {
-         eprintln!("Error: Void and Cluster matrix generation failed.");
-         // Optional: Fill output with black or a default pattern
-        for i in 0..size {
-            let base_idx_out = i * 4;
-            output[base_idx_out] = 0;
-            output[base_idx_out + 1] = 0;
-            output[base_idx_out + 2] = 0;
-            output[base_idx_out + 3] = 255;
-        }
-        return;
-    }
-
-    let matrix_height = dither_matrix.len();
-    let matrix_width = dither_matrix[0].len();
-
-    for y in 0..height {
-        for x in 0..width {
-            let idx = y * width + x;
-            let pixel = input[idx] as f32;
+    let mask = void_and_cluster_mask(DEFAULT_MATRIX_SIZE, 0);
+    let mut plane = vec![0u8; size];
+    mask_dither(input, width, height, &mask, DEFAULT_MATRIX_SIZE, 128, &mut plane);
 
-            // Get threshold from the tiled dither matrix
-            let matrix_y = y % matrix_height;
-            let matrix_x = x % matrix_width;
-            let matrix_threshold = dither_matrix[matrix_y][matrix_x] * 255.0;
-
-            // Apply threshold
-            let new_pixel_value = if pixel < matrix_threshold { 0 } else { 255 };
-
-            // Write RGBA output pixel
-            let base_idx_out = idx * 4;
-            output[base_idx_out] = new_pixel_value;
-            output[base_idx_out + 1] = new_pixel_value;
-            output[base_idx_out + 2] = new_pixel_value;
-            output[base_idx_out + 3] = 255; // Alpha
-        }
+    // Write RGBA output pixels
+    for (i, &value) in plane.iter().enumerate() {
+        output[i * 4] = value;
+        output[i * 4 + 1] = value;
+        output[i * 4 + 2] = value;
+        output[i * 4 + 3] = 255; // Alpha
     }
-} 
\ No newline at end of file
+}